@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ratatui::{backend::TestBackend, layout::Rect, Terminal};
+use rust_edit::{app::App, document::Document, ui};
+
+fn app_with_lines(lines: usize) -> App {
+    let line = "fn render(app: &mut App, frame: &mut Frame) { let pos = app.cursor(); }\n";
+    let mut app = App::new();
+    app.buffer.document = Document::from(line.repeat(lines).as_str());
+    app.window_size = Rect::new(0, 0, 120, 40);
+    app
+}
+
+fn render(c: &mut Criterion) {
+    for lines in [1_000, 100_000] {
+        let mut app = app_with_lines(lines);
+        app.jump_at_end(false);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+        c.bench_function(&format!("render {} lines", lines), |b| {
+            b.iter(|| {
+                terminal.draw(|frame| ui::render(&mut app, frame)).unwrap();
+            })
+        });
+    }
+}
+
+fn edit_and_render(c: &mut Criterion) {
+    let mut app = app_with_lines(100_000);
+    app.jump_at_end(false);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+    c.bench_function("type and render 100000 lines", |b| {
+        b.iter(|| {
+            app.insert_char('x');
+            terminal.draw(|frame| ui::render(&mut app, frame)).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, render, edit_and_render);
+criterion_main!(benches);
@@ -1,362 +1,2242 @@
-use std::{cmp::min, error, fs};
+use std::{
+    cmp::{max, min, Ordering},
+    error, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
-use ratatui::layout::Rect;
+use ratatui::layout::{Direction as SplitDirection, Rect};
 
-use crate::gap_buffer::GapBuffer;
+use crate::{
+    buffer::Buffer,
+    clipboard::{Clipboard, Register},
+    config::{Config, LineNumbers, Wrap},
+    document::Document,
+    encoding::{self, Decoded, Encoding},
+    error::FileError,
+    file::{self, Written},
+    goto,
+    history::{self, CursorState, Edit, History},
+    keymap::{Command, Keymap},
+    line_ending::{self, Format, LineEnding},
+    pane::{Node, Pane, PaneLayout},
+    search::{self, Replacer},
+    text,
+    theme::Theme,
+    word,
+};
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-const GAP_BUFFER_DEFAULT_SIZE: usize = 80;
-const QUIT_TIMES: i8 = 2;
-const DEFAULT_STATUS: &str = "Press Ctrl + C to quit, Ctrl + S to save.";
-
 #[derive(Debug)]
 pub struct Direction {
     pub x: i8,
     pub y: i8,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptMode {
+    SaveAs,
+    Search,
+    ReplaceFind,
+    ReplaceWith,
+    ReplaceConfirm,
+    GotoLine,
+    Command,
+    Open,
+}
+
+#[derive(Debug, Default)]
+struct Replace {
+    is_regex: bool,
+    pattern: String,
+    replacer: Option<Replacer>,
+    current: Option<(Range<usize>, String)>,
+    next: usize,
+    // Counted from the end of the text so it stays put while replacements
+    // before it change the length of the document.
+    end_from_end: usize,
+    count: usize,
+}
+
 #[derive(Debug)]
 pub struct App {
     prompt_cursor_position: Position,
-    quit_times: i8,
+    quit_times: u8,
+    search_origin: CursorState,
+    replace: Option<Replace>,
+    // Buffers in tab order. The slot at `current` holds a placeholder while
+    // that buffer is swapped out into `buffer`.
+    buffers: Vec<Buffer>,
+    current: usize,
+    close_confirm: bool,
+    panes: Vec<Pane>,
+    layout: Node,
+    focus: usize,
     pub running: bool,
-    pub content: Vec<GapBuffer>,
-    pub cursor_position: Position,
-    pub cursor_offset: Position,
-    pub opened_filename: String,
+    pub buffer: Buffer,
     pub window_size: Rect,
-    pub dirty: bool,
     pub is_prompt: bool,
     pub prompt: String,
+    pub prompt_mode: PromptMode,
     pub status: String,
     pub line_numbers_width: usize,
-    pub is_selecting: bool,
-    pub selecting_position: Position,
+    pub line_numbers: LineNumbers,
+    pub quit_presses: u8,
+    pub backup: bool,
+    pub smart_home: bool,
+    pub tab_width: usize,
+    pub soft_tabs: bool,
+    pub auto_indent: bool,
+    pub smart_indent: bool,
+    pub soft_wrap: bool,
+    pub wrap_at_words: bool,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    pub clipboard: Box<dyn Clipboard>,
+    pub search_query: String,
+    pub search_matches: Vec<Position>,
+    pub search_index: Option<usize>,
 }
 
 impl Default for App {
     fn default() -> Self {
-        Self {
+        let config = Config::default();
+        let mut app = Self {
             running: true,
-            content: vec![GapBuffer::new(80)],
-            cursor_position: Position { x: 0, y: 0 },
-            cursor_offset: Position { x: 0, y: 0 },
-            opened_filename: String::new(),
+            buffer: Buffer::default(),
+            buffers: vec![Buffer::default()],
+            current: 0,
+            close_confirm: false,
+            panes: vec![Pane {
+                buffer: 0,
+                cursor_position: Position { x: 0, y: 0 },
+                cursor_offset: Position { x: 0, y: 0 },
+            }],
+            layout: Node::Pane(0),
+            focus: 0,
             window_size: Rect::new(0, 0, 0, 0),
-            dirty: false,
-            quit_times: QUIT_TIMES,
+            quit_times: config.quit_times,
             is_prompt: false,
             prompt: String::new(),
+            prompt_mode: PromptMode::SaveAs,
             prompt_cursor_position: Position { x: 0, y: 0 },
-            status: DEFAULT_STATUS.into(),
+            status: String::new(),
             line_numbers_width: 4,
-            is_selecting: false,
-            selecting_position: Position { x: 0, y: 0 },
-        }
+            line_numbers: config.line_numbers,
+            quit_presses: config.quit_times,
+            backup: config.backup,
+            smart_home: config.smart_home,
+            tab_width: config.tab_width,
+            soft_tabs: config.soft_tabs,
+            auto_indent: config.auto_indent,
+            smart_indent: config.smart_indent,
+            soft_wrap: config.wrap != Wrap::Off,
+            wrap_at_words: config.wrap != Wrap::Chars,
+            theme: config.theme,
+            keymap: Keymap::default(),
+            clipboard: Box::new(Register::default()),
+            search_origin: CursorState {
+                position: Position { x: 0, y: 0 },
+                offset: Position { x: 0, y: 0 },
+            },
+            replace: None,
+            search_query: String::new(),
+            search_matches: Vec::new(),
+            search_index: None,
+        };
+        app.status = app.default_status();
+        app
     }
 }
 
+/// Width of the line numbers for a document of `lines` lines.
+pub fn gutter_width(lines: usize) -> usize {
+    max((lines as f64).log10().ceil() as usize, 4)
+}
+
+/// `path` resolved through the filesystem, so that different spellings of
+/// the same file compare equal. Files that don't exist yet keep their path
+/// as given.
+fn canonical(path: &str) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
 impl App {
     pub fn new() -> Self {
         Self::default()
     }
 
+    pub fn configure(&mut self, config: &Config) {
+        self.line_numbers = config.line_numbers;
+        self.tab_width = config.tab_width;
+        self.soft_tabs = config.soft_tabs;
+        self.auto_indent = config.auto_indent;
+        self.smart_indent = config.smart_indent;
+        self.smart_home = config.smart_home;
+        self.backup = config.backup;
+        self.quit_presses = config.quit_times;
+        self.quit_times = config.quit_times;
+        self.soft_wrap = config.wrap != Wrap::Off;
+        self.wrap_at_words = config.wrap != Wrap::Chars;
+        self.theme = config.theme.clone();
+
+        let showing_default = self.status == self.default_status();
+        self.keymap = Keymap::default();
+        for (keys, command) in &config.keys.0 {
+            self.keymap.bind(keys.clone(), *command);
+        }
+        if showing_default {
+            self.reset_status();
+        }
+    }
+
     pub fn tick(&self) {}
 
+    pub fn open_file(&mut self, path: &str) -> Result<(), FileError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.buffer.document = Document::new();
+                self.buffer.opened_filename = path.into();
+                self.buffer.backup_written = false;
+                self.buffer.history = History::default();
+                self.buffer.format = Format::default();
+                self.buffer.encoding = Encoding::Utf8;
+                self.buffer.bom = false;
+                self.status = format!("New file: {}", path);
+                self.update_line_numbers_width();
+                return Ok(());
+            }
+            Err(_) if fs::metadata(path).is_ok_and(|m| m.is_dir()) => {
+                return Err(FileError::IsDirectory(path.into()));
+            }
+            Err(err) => return Err(FileError::io(path, err)),
+        };
+
+        self.load(path, encoding::detect(&bytes));
+        let mut warnings = Vec::new();
+        if self.buffer.encoding == Encoding::Latin1 {
+            warnings.push("Not valid UTF-8, opened as Latin-1".to_string());
+        }
+        if self.buffer.format.mixed {
+            warnings.push(format!(
+                "Mixed line endings, will save as {}",
+                self.buffer.format.line_ending
+            ));
+        }
+        if !warnings.is_empty() {
+            self.status = warnings.join("; ");
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, path: &str, decoded: Decoded) {
+        let (lines, format) = line_ending::split(&decoded.text);
+        self.buffer.document = Document::from(lines.join("\n").as_str());
+        self.buffer.format = format;
+        self.buffer.encoding = decoded.encoding;
+        self.buffer.bom = decoded.bom;
+        self.buffer.opened_filename = path.into();
+        self.buffer.backup_written = false;
+        self.buffer.history = History::default();
+        self.buffer.dirty = false;
+        self.update_line_numbers_width();
+    }
+
+    /// Reads the file again, decoding it as `encoding`.
+    pub fn reopen(&mut self, encoding: Encoding) -> Result<(), FileError> {
+        if self.buffer.opened_filename.is_empty() {
+            self.status = "No file to reopen".into();
+            return Ok(());
+        }
+        if self.buffer.dirty {
+            self.status = "Save or undo changes before reopening".into();
+            return Ok(());
+        }
+
+        let path = self.buffer.opened_filename.clone();
+        let bytes = fs::read(&path).map_err(|err| FileError::io(&path, err))?;
+        let decoded = encoding::decode(&bytes, encoding).ok_or(FileError::Decode {
+            path: path.clone(),
+            encoding,
+        })?;
+
+        let y = self.get_cursor_position().y;
+        self.load(&path, decoded);
+        self.buffer.is_selecting = false;
+        self.search_matches.clear();
+        self.search_index = None;
+        self.set_cursor(Position {
+            x: 0,
+            y: min(y, self.buffer.document.len_lines() - 1),
+        });
+        self.status = format!("Reopened as {}", encoding);
+        Ok(())
+    }
+
+    /// Sets the encoding used for the next save.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        let bom = match encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => true,
+            Encoding::Utf8 => self.buffer.bom && self.buffer.encoding == Encoding::Utf8,
+            Encoding::Latin1 => false,
+        };
+        if self.buffer.encoding != encoding || self.buffer.bom != bom {
+            self.buffer.encoding = encoding;
+            self.buffer.bom = bom;
+            self.buffer.history.mark_unsaved();
+            self.buffer.dirty = true;
+        }
+        self.status = format!("Encoding: {}", encoding);
+    }
+
     pub fn quit(&mut self) {
-        if !self.dirty {
+        let modified: Vec<String> = (0..self.buffers.len())
+            .map(|i| self.buffer_at(i))
+            .filter(|buffer| buffer.dirty)
+            .map(|buffer| buffer.name().to_string())
+            .collect();
+        if modified.is_empty() {
             self.running = false;
             return;
         }
 
-        self.quit_times -= 1;
-        if self.quit_times <= 0 {
+        self.quit_times = self.quit_times.saturating_sub(1);
+        if self.quit_times == 0 {
             self.running = false;
+        } else {
+            self.status = match self.keymap.keys_for(Command::Quit) {
+                Some(keys) => format!(
+                    "Unsaved changes in {}. Press {} again to quit.",
+                    modified.join(", "),
+                    keys
+                ),
+                None => format!("Unsaved changes in {}.", modified.join(", ")),
+            };
         }
     }
 
-    pub fn set_dirty(&mut self) {
-        self.dirty = true;
+    fn after_edit(&mut self) {
+        self.buffer.dirty = self.buffer.history.is_modified();
+        self.search_matches.clear();
+        self.search_index = None;
         self.reset_quit();
     }
 
+    /// The hint shown when there's nothing else to report, naming whatever
+    /// keys quit and save are bound to.
+    fn default_status(&self) -> String {
+        let hints: Vec<String> = [(Command::Quit, "quit"), (Command::Save, "save")]
+            .into_iter()
+            .filter_map(|(command, action)| {
+                let keys = self.keymap.keys_for(command)?;
+                Some(format!("{} to {}", keys, action))
+            })
+            .collect();
+        if hints.is_empty() {
+            String::new()
+        } else {
+            format!("Press {}.", hints.join(", "))
+        }
+    }
+
+    pub fn reset_status(&mut self) {
+        let status = self.default_status();
+        if self.status != status {
+            self.status = status;
+        }
+    }
+
     pub fn reset_quit(&mut self) {
-        self.quit_times = QUIT_TIMES;
+        self.quit_times = self.quit_presses;
+        self.close_confirm = false;
     }
 
-    pub fn get_cursor_position(&self) -> Position {
-        if self.is_prompt {
-            Position {
-                x: self.prompt_cursor_position.x + self.cursor_offset.x,
-                y: self.prompt_cursor_position.y + self.cursor_offset.y,
-            }
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn current_buffer(&self) -> usize {
+        self.current
+    }
+
+    /// Buffer at `index` in tab order.
+    pub fn buffer_at(&self, index: usize) -> &Buffer {
+        if index == self.current {
+            &self.buffer
         } else {
-            Position {
-                x: self.cursor_position.x + self.cursor_offset.x,
-                y: self.cursor_position.y + self.cursor_offset.y,
-            }
+            &self.buffers[index]
         }
     }
 
-    fn update_line_numbers_width(&mut self) {
-        self.line_numbers_width =
-            std::cmp::max((self.content.len() as f64).log10().ceil() as usize, 4);
+    pub fn switch_buffer(&mut self, index: usize) {
+        if index == self.current || index >= self.buffers.len() || self.is_prompt {
+            return;
+        }
+
+        self.swap_in(index);
+        self.after_switch();
     }
 
-    fn push_to_content(&mut self, s: GapBuffer) {
-        self.content.push(s);
-        self.update_line_numbers_width();
+    fn swap_in(&mut self, index: usize) {
+        std::mem::swap(&mut self.buffer, &mut self.buffers[self.current]);
+        self.buffer = std::mem::take(&mut self.buffers[index]);
+        self.current = index;
+        self.panes[self.focus].buffer = index;
     }
 
-    fn insert_to_content(&mut self, index: usize, s: GapBuffer) {
-        self.content.insert(index, s);
+    pub fn cycle_buffer(&mut self, forward: bool) {
+        let count = self.buffers.len();
+        let index = if forward {
+            (self.current + 1) % count
+        } else {
+            (self.current + count - 1) % count
+        };
+        self.switch_buffer(index);
+    }
+
+    fn after_switch(&mut self) {
+        self.search_matches.clear();
+        self.search_index = None;
         self.update_line_numbers_width();
+        // The buffer may have been edited from another pane, and the pane
+        // may have changed size, while the cursor was parked.
+        let pos = self.get_cursor_position();
+        let y = min(pos.y, self.buffer.document.len_lines() - 1);
+        self.set_cursor(Position {
+            x: min(pos.x, self.buffer.document.line_len(y)),
+            y,
+        });
+        self.status = format!(
+            "{} ({}/{})",
+            self.buffer.name(),
+            self.current + 1,
+            self.buffers.len()
+        );
+    }
+
+    /// Opens `path` in a new buffer, or switches to it when it is already
+    /// open. An untouched empty buffer is reused.
+    pub fn open_buffer(&mut self, path: &str) -> Result<(), FileError> {
+        let target = canonical(path);
+        if let Some(index) = (0..self.buffers.len()).find(|&i| {
+            let name = &self.buffer_at(i).opened_filename;
+            !name.is_empty() && canonical(name) == target
+        }) {
+            self.switch_buffer(index);
+            return Ok(());
+        }
+        if self.buffer.is_scratch() {
+            return self.open_file(path);
+        }
+
+        let previous = self.current;
+        self.buffers.push(Buffer::default());
+        self.switch_buffer(self.buffers.len() - 1);
+        let status = self.status.clone();
+        if let Err(err) = self.open_file(path) {
+            self.remove_buffer();
+            self.switch_buffer(previous);
+            return Err(err);
+        }
+        if self.status == status {
+            self.status = format!("Opened {}", path);
+        }
+        Ok(())
     }
 
-    fn remove_from_content(&mut self, index: usize) -> String {
-        let s = self.content.remove(index);
-        self.line_numbers_width =
-            std::cmp::max((self.content.len() as f64).log10().ceil() as usize, 4);
-        s.to_string()
+    /// Lists the active key bindings in a new buffer.
+    pub fn show_bindings(&mut self) {
+        if self.is_prompt {
+            return;
+        }
+        let mut bindings: Vec<(String, String)> = self
+            .keymap
+            .bindings()
+            .iter()
+            .map(|(keys, command)| (command.to_string(), keys.to_string()))
+            .collect();
+        bindings.sort();
+        let width = bindings
+            .iter()
+            .map(|(_, keys)| keys.len())
+            .max()
+            .unwrap_or(0);
+        let listing: Vec<String> = bindings
+            .iter()
+            .map(|(command, keys)| format!("{:<width$}  {}", keys, command))
+            .collect();
+
+        if !self.buffer.is_scratch() {
+            self.buffers.push(Buffer::default());
+            self.switch_buffer(self.buffers.len() - 1);
+        }
+        self.buffer.document = Document::from(listing.join("\n").as_str());
+        self.update_line_numbers_width();
+        self.set_cursor(Position { x: 0, y: 0 });
+        self.status = format!("{} key bindings", listing.len());
     }
 
-    pub fn enter_prompt(&mut self) {
-        self.is_prompt = true;
-        self.prompt_cursor_position = self.cursor_position;
-        self.cursor_position.y = self.window_size.height as usize;
-        self.cursor_position.x = 0;
+    pub fn start_open(&mut self) {
+        if !self.is_prompt {
+            self.enter_prompt(PromptMode::Open);
+        }
     }
 
-    pub fn exit_prompt(&mut self) {
+    /// Closes the current buffer, asking again first when it has unsaved
+    /// changes.
+    pub fn close_buffer(&mut self) {
         if self.is_prompt {
-            self.is_prompt = false;
-            self.prompt = String::new();
-            self.cursor_position = self.prompt_cursor_position;
-            self.prompt_cursor_position = Position { x: 0, y: 0 };
+            return;
+        }
+        if self.buffer.dirty && !self.close_confirm {
+            self.close_confirm = true;
+            self.status = match self.keymap.keys_for(Command::CloseBuffer) {
+                Some(keys) => format!(
+                    "{} has unsaved changes. Press {} again to close it.",
+                    self.buffer.name(),
+                    keys
+                ),
+                None => format!("{} has unsaved changes.", self.buffer.name()),
+            };
+            return;
         }
+
+        self.close_confirm = false;
+        self.remove_buffer();
+        self.after_switch();
     }
 
-    pub fn save_to_file(&mut self) {
-        if self.opened_filename.is_empty() || self.is_prompt {
-            self.enter_prompt();
+    fn remove_buffer(&mut self) {
+        if self.buffers.len() == 1 {
+            self.buffer = Buffer::default();
             return;
         }
 
-        self.dirty = false;
-        self.status = format!("Saved to {}", self.opened_filename);
-        let _ = fs::write(
-            &self.opened_filename,
-            self.content
-                .iter()
-                .map(|b| b.to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        );
+        let removed = self.current;
+        self.buffers.remove(removed);
+        self.current = min(removed, self.buffers.len() - 1);
+        self.buffer = std::mem::take(&mut self.buffers[self.current]);
+
+        for pane in &mut self.panes {
+            if pane.buffer == removed {
+                pane.buffer = self.current;
+                pane.cursor_position = Position { x: 0, y: 0 };
+                pane.cursor_offset = Position { x: 0, y: 0 };
+            } else if pane.buffer > removed {
+                pane.buffer -= 1;
+            }
+        }
     }
 
-    pub fn insert_char(&mut self, c: char) {
+    pub fn focused_pane(&self) -> usize {
+        self.focus
+    }
+
+    /// Area below the tab line and above the status bar shared by the panes.
+    pub fn text_area(&self) -> Rect {
+        Rect {
+            x: 0,
+            y: 1,
+            width: self.window_size.width,
+            height: self.window_size.height.saturating_sub(3),
+        }
+    }
+
+    pub fn pane_layout(&self, area: Rect) -> PaneLayout {
+        self.layout.layout(area)
+    }
+
+    fn pane_area(&self) -> Rect {
+        self.pane_layout(self.text_area())
+            .panes
+            .into_iter()
+            .find(|&(pane, _)| pane == self.focus)
+            .map_or(Rect::default(), |(_, area)| area)
+    }
+
+    /// Buffer shown in pane `index` with its cursor, as a document position,
+    /// and scroll offset.
+    pub fn pane_view(&self, index: usize) -> (&Buffer, Position, Position) {
+        if index == self.focus {
+            return (
+                &self.buffer,
+                self.get_cursor_position(),
+                self.buffer.cursor_offset,
+            );
+        }
+
+        let pane = &self.panes[index];
+        let buffer = self.buffer_at(pane.buffer);
+        let y = pane.cursor_offset.y + pane.cursor_position.y;
+        let x = if y < buffer.document.len_lines() {
+            let column = pane.cursor_offset.x + pane.cursor_position.x;
+            text::index_at_column(&buffer.document.line(y), column, self.tab_width)
+        } else {
+            0
+        };
+        (buffer, Position { x, y }, pane.cursor_offset)
+    }
+
+    /// Splits the focused pane, showing the same buffer in both halves.
+    pub fn split_pane(&mut self, direction: SplitDirection) {
         if self.is_prompt {
-            self.prompt.insert(self.cursor_position.x, c);
-            self.move_cursor(Direction { x: 1, y: 0 }, false);
             return;
         }
+        let new = self.panes.len();
+        self.panes.push(Pane {
+            buffer: self.current,
+            cursor_position: self.buffer.cursor_position,
+            cursor_offset: self.buffer.cursor_offset,
+        });
+        self.layout.split(self.focus, new, direction);
+        self.set_cursor(self.get_cursor_position());
+    }
 
-        while self.cursor_position.y >= self.content.len() {
-            self.push_to_content(GapBuffer::new(GAP_BUFFER_DEFAULT_SIZE));
+    pub fn focus_next_pane(&mut self, forward: bool) {
+        if self.is_prompt || self.panes.len() == 1 {
+            return;
         }
+        let order = self.layout.panes();
+        let i = order.iter().position(|&p| p == self.focus).unwrap_or(0);
+        let next = if forward {
+            order[(i + 1) % order.len()]
+        } else {
+            order[(i + order.len() - 1) % order.len()]
+        };
 
-        self.content[self.cursor_position.y + self.cursor_offset.y]
-            .insert_at(self.cursor_position.x + self.cursor_offset.x, c);
-        self.move_cursor(Direction { x: 1, y: 0 }, false);
+        self.panes[self.focus].cursor_position = self.buffer.cursor_position;
+        self.panes[self.focus].cursor_offset = self.buffer.cursor_offset;
+        self.load_pane(next);
     }
 
-    pub fn add_new_line(&mut self) {
+    /// Makes `index` the focused pane, bringing in its buffer and cursor.
+    fn load_pane(&mut self, index: usize) {
+        self.focus = index;
+        let pane = self.panes[index];
+        if pane.buffer != self.current {
+            self.swap_in(pane.buffer);
+        }
+        self.buffer.cursor_position = pane.cursor_position;
+        self.buffer.cursor_offset = pane.cursor_offset;
+        self.buffer.is_selecting = false;
+        self.after_switch();
+    }
+
+    pub fn close_pane(&mut self) {
         if self.is_prompt {
-            self.opened_filename = self.prompt.clone();
-            self.exit_prompt();
-            self.save_to_file();
+            return;
+        }
+        if self.panes.len() == 1 {
+            self.status = "Can't close the only pane".into();
             return;
         }
 
-        let pos = self.get_cursor_position();
+        let order = self.layout.panes();
+        let i = order.iter().position(|&p| p == self.focus).unwrap_or(0);
+        let removed = self.focus;
+        self.layout.remove(removed);
+        self.panes.remove(removed);
+
+        let order = self.layout.panes();
+        self.load_pane(order[min(i, order.len() - 1)]);
+    }
 
-        while pos.y >= self.content.len() {
-            self.push_to_content(GapBuffer::new(GAP_BUFFER_DEFAULT_SIZE));
+    pub fn resize_pane(&mut self, delta: i16) {
+        if self.is_prompt {
+            return;
         }
+        if self.layout.resize(self.focus, delta) {
+            self.set_cursor(self.get_cursor_position());
+        }
+    }
 
-        let current_line = &mut self.content[pos.y];
+    /// Cursor position in the document, with `x` as a character index.
+    pub fn get_cursor_position(&self) -> Position {
+        let y = self.cursor_screen_position().y + self.buffer.cursor_offset.y;
+        let line = if y < self.buffer.document.len_lines() {
+            self.buffer.document.line(y)
+        } else {
+            String::new()
+        };
+
+        Position {
+            x: text::index_at_column(&line, self.cursor_column(), self.tab_width),
+            y,
+        }
+    }
 
-        if current_line.len() > pos.x {
-            let new_line = current_line.split_off(pos.x);
+    /// Display column of the cursor within its line.
+    pub fn cursor_column(&self) -> usize {
+        self.cursor_screen_position().x + self.buffer.cursor_offset.x
+    }
 
-            self.insert_to_content(pos.y + 1, new_line);
+    fn cursor_screen_position(&self) -> Position {
+        if self.is_prompt {
+            self.prompt_cursor_position
         } else {
-            self.insert_to_content(pos.y + 1, GapBuffer::new(GAP_BUFFER_DEFAULT_SIZE));
+            self.buffer.cursor_position
         }
+    }
 
-        self.cursor_position.x = 0;
-        self.move_cursor(Direction { x: 0, y: -1 }, false);
+    fn update_line_numbers_width(&mut self) {
+        self.line_numbers_width = gutter_width(self.buffer.document.len_lines());
     }
 
-    pub fn pop_char(&mut self) {
+    pub fn enter_prompt(&mut self, mode: PromptMode) {
+        self.is_prompt = true;
+        self.prompt_mode = mode;
+        self.prompt_cursor_position = self.buffer.cursor_position;
+        self.buffer.cursor_position.y = self.window_size.height as usize;
+        self.buffer.cursor_position.x = 0;
+    }
+
+    pub fn exit_prompt(&mut self) {
         if self.is_prompt {
-            if self.prompt.len() == 0 {
-                return;
-            }
-            self.prompt.pop();
-            self.move_cursor(Direction { x: -1, y: 0 }, false);
-            return;
+            self.is_prompt = false;
+            self.prompt = String::new();
+            self.buffer.cursor_position = self.prompt_cursor_position;
+            self.prompt_cursor_position = Position { x: 0, y: 0 };
         }
+    }
 
-        if self.content.len() == 0 {
+    pub fn cancel_prompt(&mut self) {
+        if !self.is_prompt {
+            self.buffer.is_selecting = false;
+            self.search_matches.clear();
+            self.search_index = None;
             return;
         }
 
-        let pos = self.get_cursor_position();
+        let mode = self.prompt_mode;
+        self.exit_prompt();
+
+        match mode {
+            PromptMode::Search => {
+                self.restore_cursor(self.search_origin);
+                self.search_matches.clear();
+                self.search_index = None;
+            }
+            PromptMode::ReplaceFind | PromptMode::ReplaceWith | PromptMode::ReplaceConfirm => {
+                self.finish_replace();
+            }
+            PromptMode::SaveAs | PromptMode::GotoLine | PromptMode::Command | PromptMode::Open => {}
+        }
+    }
 
-        if self.content[pos.y].len() == 0 {
-            self.remove_from_content(pos.y);
+    pub fn prompt_label(&self) -> &'static str {
+        match self.prompt_mode {
+            PromptMode::SaveAs => "Save as: ",
+            PromptMode::Search => "Search: ",
+            PromptMode::ReplaceFind => match &self.replace {
+                Some(replace) if replace.is_regex => "Replace regex: ",
+                _ => "Replace: ",
+            },
+            PromptMode::ReplaceWith => "With: ",
+            PromptMode::ReplaceConfirm => "Replace? (y)es (n)o (a)ll (q)uit ",
+            PromptMode::GotoLine => "Go to line: ",
+            PromptMode::Command => "Command: ",
+            PromptMode::Open => "Open: ",
+        }
+    }
 
-            self.move_cursor(Direction { x: 0, y: 1 }, false);
-        } else if self.cursor_position.x == 0 && self.cursor_position.y > 0 {
-            let lower_line = self.remove_from_content(pos.y);
+    pub fn save_to_file(&mut self) -> AppResult<()> {
+        if self.buffer.opened_filename.is_empty() || self.is_prompt {
+            self.enter_prompt(PromptMode::SaveAs);
+            return Ok(());
+        }
 
-            self.cursor_position.x = self.content[pos.y - 1].len();
+        let contents = line_ending::join(self.buffer.document.lines(), self.buffer.format);
+        let bytes = encoding::encode(&contents, self.buffer.encoding, self.buffer.bom).map_err(
+            |character| FileError::Encode {
+                path: self.buffer.opened_filename.clone(),
+                encoding: self.buffer.encoding,
+                character,
+            },
+        )?;
+        let backup = self.backup && !self.buffer.backup_written;
 
-            self.content[pos.y - 1].push_str(&lower_line);
+        let written = file::write_atomic(Path::new(&self.buffer.opened_filename), &bytes, backup)
+            .map_err(|err| FileError::io(&self.buffer.opened_filename, err))?;
 
-            self.move_cursor(Direction { x: 0, y: 1 }, false);
-        } else if !(self.cursor_position.x == 0 && self.cursor_position.y == 0) {
-            self.content[pos.y].remove_at(pos.x - 1);
+        self.buffer.backup_written |= backup;
+        self.buffer.history.mark_saved();
+        self.buffer.format.mixed = false;
+        self.buffer.dirty = false;
+        self.status = match written {
+            Written::Atomic => format!("Saved to {}", self.buffer.opened_filename),
+            Written::InPlace => format!(
+                "Saved to {} in place, not atomically",
+                self.buffer.opened_filename
+            ),
+        };
+        Ok(())
+    }
 
-            self.move_cursor(Direction { x: -1, y: 0 }, false);
-        }
+    fn prompt_byte_index(&self, index: usize) -> usize {
+        self.prompt
+            .char_indices()
+            .nth(index)
+            .map_or(self.prompt.len(), |(i, _)| i)
     }
 
-    pub fn move_cursor(&mut self, direction: Direction, is_selection: bool) {
-        self.reset_quit();
+    pub fn show_error(&mut self, err: &dyn error::Error) {
+        self.status = format!("Error: {}", err);
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        if self.is_prompt && self.prompt_mode == PromptMode::ReplaceConfirm {
+            self.answer_replace(c);
+            return;
+        }
 
         if self.is_prompt {
-            if direction.x < 0 && self.cursor_position.x > 0 {
-                self.cursor_position.x -= 1;
-            } else if direction.x > 0 && self.prompt.len() > self.cursor_position.x {
-                self.cursor_position.x += 1;
+            let i = self.prompt_byte_index(self.buffer.cursor_position.x);
+            self.prompt.insert(i, c);
+            self.buffer.cursor_position.x += 1;
+            if self.prompt_mode == PromptMode::Search {
+                self.update_search();
             }
             return;
         }
 
-        // TODO: move if selection
-        if is_selection && !self.is_selecting {
-            self.is_selecting = true;
-            self.selecting_position = self.get_cursor_position();
+        self.buffer.history.begin(self.cursor_state());
+        self.delete_selection();
+        let mut pos = self.get_cursor_position();
+        if self.auto_indent && self.smart_indent && matches!(c, '}' | ')' | ']') {
+            let line = self.buffer.document.line(pos.y);
+            if line.chars().all(char::is_whitespace) {
+                let n = min(self.outdent_width(&line), pos.x);
+                self.remove_text(Position { x: 0, y: pos.y }, Position { x: n, y: pos.y });
+                pos.x -= n;
+            }
         }
+        let end = self.insert_text_at(pos, &c.to_string());
+        self.set_cursor(end);
+        self.buffer.history.end_typing(self.cursor_state());
+        self.after_edit();
+    }
 
-        if self.status != DEFAULT_STATUS {
-            self.status = DEFAULT_STATUS.into();
+    /// Inserts a tab, or spaces up to the next tab stop with soft tabs. A
+    /// selection spanning several lines is indented instead.
+    pub fn insert_tab(&mut self) {
+        if self.is_prompt {
+            return;
+        }
+        if self
+            .selection()
+            .is_some_and(|(start, end)| start.y != end.y)
+        {
+            self.shift_lines(true);
+            return;
         }
 
+        self.buffer.history.begin(self.cursor_state());
+        self.delete_selection();
         let pos = self.get_cursor_position();
+        let indent = if self.soft_tabs {
+            let column = text::column_of(&self.buffer.document.line(pos.y), pos.x, self.tab_width);
+            let tab_width = self.tab_width.max(1);
+            " ".repeat(tab_width - column % tab_width)
+        } else {
+            "\t".into()
+        };
+        let end = self.insert_text_at(pos, &indent);
+        self.set_cursor(end);
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+    }
 
-        if direction.x < 0 {
-            self.move_cursor_left(pos);
-        } else if direction.x > 0 {
-            self.move_cursor_right(pos);
+    /// Removes one level of indentation from the current or selected lines.
+    pub fn outdent(&mut self) {
+        if self.is_prompt {
+            return;
+        }
+        self.shift_lines(false);
+    }
+
+    fn indent_unit(&self) -> String {
+        if self.soft_tabs {
+            " ".repeat(self.tab_width)
+        } else {
+            "\t".into()
         }
+    }
 
-        if direction.y > 0 && pos.y > 0 {
-            self.move_cursor_up(pos);
-        } else if direction.y < 0 && pos.y < self.content.len().saturating_sub(1) {
-            self.move_cursor_down(pos);
+    /// Characters at the start of `line` making up one indentation level.
+    fn outdent_width(&self, line: &str) -> usize {
+        if line.starts_with('\t') {
+            1
+        } else {
+            line.chars()
+                .take(self.tab_width)
+                .take_while(|&c| c == ' ')
+                .count()
         }
     }
 
-    fn move_cursor_left(&mut self, pos: Position) {
-        if pos.x > 0 {
-            if self.cursor_position.x == 0 {
-                if self.cursor_offset.x > 0 {
-                    self.cursor_offset.x -= 1;
+    fn shift_lines(&mut self, indent: bool) {
+        let mut cursor = self.get_cursor_position();
+        let mut anchor = self.buffer.selecting_position;
+        let (first, last) = match self.selection() {
+            // A selection ending at column 0 doesn't include that line.
+            Some((start, end)) if end.x == 0 && end.y > start.y => (start.y, end.y - 1),
+            Some((start, end)) => (start.y, end.y),
+            None => (cursor.y, cursor.y),
+        };
+
+        self.buffer.history.begin(self.cursor_state());
+        for y in first..=last {
+            let line = self.buffer.document.line(y);
+            let shift = if indent {
+                if line.is_empty() {
+                    continue;
                 }
+                let unit = self.indent_unit();
+                self.insert_text_at(Position { x: 0, y }, &unit);
+                unit.chars().count() as isize
             } else {
-                self.cursor_position.x -= 1;
+                let n = self.outdent_width(&line);
+                if n == 0 {
+                    continue;
+                }
+                self.remove_text(Position { x: 0, y }, Position { x: n, y });
+                -(n as isize)
+            };
+
+            for pos in [&mut cursor, &mut anchor] {
+                if pos.y == y {
+                    pos.x = pos.x.saturating_add_signed(shift);
+                }
+            }
+        }
+        self.buffer.selecting_position = anchor;
+        self.set_cursor(cursor);
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+    }
+
+    pub fn add_new_line(&mut self) {
+        if self.is_prompt {
+            self.submit_prompt();
+            return;
+        }
+
+        self.buffer.history.begin(self.cursor_state());
+        self.delete_selection();
+        let pos = self.get_cursor_position();
+        let (text, split) = if self.auto_indent {
+            self.new_line_text(pos)
+        } else {
+            ("\n".to_string(), 1)
+        };
+        self.insert_text_at(pos, &text);
+        self.set_cursor(history::end_of(pos, &text[..split]));
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+    }
+
+    /// Text inserted by Enter at `pos`, carrying over the line's indentation
+    /// plus, with smart indent, a level after an opening bracket or colon,
+    /// and the byte length of the part to leave the cursor after. Between a
+    /// pair of brackets the closing one goes on a line of its own.
+    fn new_line_text(&self, pos: Position) -> (String, usize) {
+        let line = self.buffer.document.line(pos.y);
+        let before: String = line.chars().take(pos.x).collect();
+        let after: String = line.chars().skip(pos.x).collect();
+        let indent: String = before.chars().take_while(|c| c.is_whitespace()).collect();
+
+        let mut text = format!("\n{}", indent);
+        if !self.smart_indent {
+            let split = text.len();
+            return (text, split);
+        }
+        let close = match before.trim_end().chars().last() {
+            Some('{') => Some('}'),
+            Some('(') => Some(')'),
+            Some('[') => Some(']'),
+            Some(':') => None,
+            _ => {
+                let split = text.len();
+                return (text, split);
             }
-        } else if self.cursor_position.y > 0 {
-            self.cursor_position.y -= 1;
-            let len = self.content[pos.y - 1].len();
-            self.cursor_position.x = min(len, self.window_size.width.into());
-            self.cursor_offset.x = len - self.cursor_position.x;
+        };
+
+        text.push_str(&self.indent_unit());
+        let split = text.len();
+        if close.is_some_and(|close| after.trim_start().starts_with(close)) {
+            text.push('\n');
+            text.push_str(&indent);
         }
+        (text, split)
     }
 
-    fn move_cursor_right(&mut self, pos: Position) {
-        if let Some(line) = self.content.get(pos.y) {
-            if line.len() > pos.x {
-                if self.window_size.width > (self.cursor_position.x + 1) as u16 {
-                    self.cursor_position.x += 1;
-                } else {
-                    self.cursor_offset.x += 1;
+    fn submit_prompt(&mut self) {
+        match self.prompt_mode {
+            PromptMode::SaveAs => {
+                self.buffer.opened_filename = self.prompt.clone();
+                self.exit_prompt();
+                if let Err(err) = self.save_to_file() {
+                    self.show_error(err.as_ref());
+                }
+            }
+            PromptMode::Search => {
+                self.exit_prompt();
+                self.status = self.search_status();
+            }
+            PromptMode::ReplaceFind => {
+                let pattern = self.prompt.clone();
+                self.exit_prompt();
+
+                let Some(replace) = &mut self.replace else {
+                    return;
+                };
+                if pattern.is_empty() {
+                    self.replace = None;
+                    self.status = "Nothing to replace".into();
+                    return;
                 }
-            } else if line.len() == pos.x && pos.y < self.content.len() - 1 {
-                self.cursor_offset.x = 0;
-                self.cursor_position.x = 0;
-                if self.cursor_position.y + 1 > self.window_size.height.into() {
-                    self.cursor_offset.y += 1;
-                } else {
-                    self.cursor_position.y += 1;
+                if let Err(err) = search::compile(&pattern, replace.is_regex) {
+                    self.replace = None;
+                    self.show_error(&err);
+                    return;
+                }
+                replace.pattern = pattern;
+                self.enter_prompt(PromptMode::ReplaceWith);
+            }
+            PromptMode::ReplaceWith => {
+                let replacement = self.prompt.clone();
+                self.exit_prompt();
+
+                let Some(replace) = &mut self.replace else {
+                    return;
+                };
+                match Replacer::new(&replace.pattern, &replacement, replace.is_regex) {
+                    Ok(replacer) => replace.replacer = Some(replacer),
+                    Err(err) => {
+                        self.replace = None;
+                        self.show_error(&err);
+                        return;
+                    }
+                }
+                self.enter_prompt(PromptMode::ReplaceConfirm);
+                self.next_replace();
+            }
+            PromptMode::ReplaceConfirm => self.answer_replace('y'),
+            PromptMode::GotoLine => {
+                let input = self.prompt.clone();
+                self.exit_prompt();
+                self.goto(&input);
+            }
+            PromptMode::Command => {
+                let input = self.prompt.clone();
+                self.exit_prompt();
+                self.run_command(&input);
+            }
+            PromptMode::Open => {
+                let path = self.prompt.clone();
+                self.exit_prompt();
+                if let Err(err) = self.open_buffer(&path) {
+                    self.show_error(&err);
                 }
             }
         }
     }
 
-    fn move_cursor_up(&mut self, pos: Position) {
-        if self.cursor_position.y == 0 {
-            if self.cursor_offset.y > 0 {
-                self.cursor_offset.y -= 1;
+    pub fn pop_char(&mut self) {
+        if self.is_prompt {
+            if self.buffer.cursor_position.x == 0 {
+                return;
             }
-        } else {
-            self.cursor_position.y -= 1;
+            let start = text::previous_grapheme(&self.prompt, self.buffer.cursor_position.x);
+            let range = self.prompt_byte_index(start)
+                ..self.prompt_byte_index(self.buffer.cursor_position.x);
+            self.prompt.replace_range(range, "");
+            self.buffer.cursor_position.x = start;
+            if self.prompt_mode == PromptMode::Search {
+                self.update_search();
+            }
+            return;
         }
 
-        if self.cursor_position.x > self.content[pos.y - 1].len() {
-            self.cursor_position.x = self.content[pos.y - 1].len();
-            self.cursor_offset.x = 0;
+        // Always goes through `delete_selection` so an empty selection left
+        // behind by moving back and forth is dropped before the edit.
+        self.buffer.history.begin(self.cursor_state());
+        if !self.delete_selection() {
+            let pos = self.get_cursor_position();
+            let start = if pos.x > 0 {
+                Some(Position {
+                    x: text::previous_grapheme(&self.buffer.document.line(pos.y), pos.x),
+                    y: pos.y,
+                })
+            } else if pos.y > 0 {
+                Some(Position {
+                    x: self.buffer.document.line_len(pos.y - 1),
+                    y: pos.y - 1,
+                })
+            } else {
+                None
+            };
+
+            if let Some(start) = start {
+                self.remove_text(start, pos);
+                self.set_cursor(start);
+            }
         }
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
     }
 
-    fn move_cursor_down(&mut self, pos: Position) {
-        if self.window_size.height.saturating_sub(4) > self.cursor_position.y as u16 {
-            self.cursor_position.y += 1;
-        } else {
-            self.cursor_offset.y += 1;
+    pub fn delete_char(&mut self) {
+        if self.is_prompt {
+            let end = text::next_grapheme(&self.prompt, self.buffer.cursor_position.x);
+            let range =
+                self.prompt_byte_index(self.buffer.cursor_position.x)..self.prompt_byte_index(end);
+            if !range.is_empty() {
+                self.prompt.replace_range(range, "");
+                if self.prompt_mode == PromptMode::Search {
+                    self.update_search();
+                }
+            }
+            return;
+        }
+
+        self.buffer.history.begin(self.cursor_state());
+        if !self.delete_selection() {
+            let pos = self.get_cursor_position();
+            let end = if pos.x < self.buffer.document.line_len(pos.y) {
+                Some(Position {
+                    x: text::next_grapheme(&self.buffer.document.line(pos.y), pos.x),
+                    y: pos.y,
+                })
+            } else if pos.y + 1 < self.buffer.document.len_lines() {
+                Some(Position { x: 0, y: pos.y + 1 })
+            } else {
+                None
+            };
+
+            if let Some(end) = end {
+                self.remove_text(pos, end);
+                self.set_cursor(pos);
+            }
+        }
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+    }
+
+    pub fn undo(&mut self) {
+        if self.is_prompt {
+            return;
         }
 
-        if self.cursor_position.x > self.content[pos.y + 1].len() {
-            self.cursor_position.x = self.content[pos.y + 1].len();
-            self.cursor_offset.x = 0;
+        let Some(change) = self.buffer.history.undo() else {
+            self.status = "Nothing to undo".into();
+            return;
+        };
+
+        for edit in change.edits.iter().rev() {
+            match edit {
+                Edit::Insert { at, text } => {
+                    self.remove_content(*at, history::end_of(*at, text));
+                }
+                Edit::Delete { at, text } => {
+                    self.insert_content(*at, text);
+                }
+            }
         }
+
+        self.buffer.is_selecting = false;
+        self.restore_cursor(change.before);
+        self.after_edit();
     }
 
-    pub fn jump_at_end_line(&mut self) {
-        let pos = self.get_cursor_position();
-        let len = self.content[pos.y].len();
-        let width = self.window_size.width.into();
+    pub fn redo(&mut self) {
+        if self.is_prompt {
+            return;
+        }
 
-        if len > width {
-            self.cursor_position.x = width;
-            self.cursor_offset.x = len - width;
-        } else {
-            self.cursor_position.x = len - self.cursor_offset.x;
+        let Some(change) = self.buffer.history.redo() else {
+            self.status = "Nothing to redo".into();
+            return;
+        };
+
+        for edit in change.edits.iter() {
+            match edit {
+                Edit::Insert { at, text } => {
+                    self.insert_content(*at, text);
+                }
+                Edit::Delete { at, text } => {
+                    self.remove_content(*at, history::end_of(*at, text));
+                }
+            }
+        }
+
+        self.buffer.is_selecting = false;
+        self.restore_cursor(change.after);
+        self.after_edit();
+    }
+
+    fn cursor_state(&self) -> CursorState {
+        CursorState {
+            position: self.buffer.cursor_position,
+            offset: self.buffer.cursor_offset,
         }
     }
 
-    pub fn jump_at_start_line(&mut self) {
-        self.cursor_position.x = 0;
-        self.cursor_offset.x = 0;
+    fn restore_cursor(&mut self, state: CursorState) {
+        self.buffer.cursor_position = state.position;
+        self.buffer.cursor_offset = state.offset;
+    }
+
+    fn insert_text_at(&mut self, at: Position, text: &str) -> Position {
+        self.buffer.history.push(Edit::Insert {
+            at,
+            text: text.into(),
+        });
+        self.insert_content(at, text)
+    }
+
+    fn full_text(&self) -> String {
+        self.buffer.document.to_string()
+    }
+
+    fn text_in(&self, start: Position, end: Position) -> String {
+        self.buffer.document.slice(start, end)
+    }
+
+    fn remove_text(&mut self, start: Position, end: Position) -> String {
+        let text = self.remove_content(start, end);
+        self.buffer.history.push(Edit::Delete {
+            at: start,
+            text: text.clone(),
+        });
+        text
+    }
+
+    fn insert_content(&mut self, at: Position, text: &str) -> Position {
+        let end = self.buffer.document.insert(at, text);
+        self.update_line_numbers_width();
+        end
+    }
+
+    fn remove_content(&mut self, start: Position, end: Position) -> String {
+        let removed = self.buffer.document.remove(start, end);
+        self.update_line_numbers_width();
+        removed
+    }
+
+    fn view_height(&self) -> usize {
+        max(self.pane_area().height as usize, 1)
+    }
+
+    fn view_width(&self) -> usize {
+        max(
+            (self.pane_area().width as usize).saturating_sub(self.line_numbers_width + 1),
+            1,
+        )
+    }
+
+    /// Puts the cursor at a document position, scrolling it into view.
+    fn set_cursor(&mut self, pos: Position) {
+        let height = self.view_height();
+        let width = self.view_width();
+        let column = text::column_of(&self.buffer.document.line(pos.y), pos.x, self.tab_width);
+
+        if pos.y < self.buffer.cursor_offset.y {
+            self.buffer.cursor_offset.y = pos.y;
+        } else if pos.y >= self.buffer.cursor_offset.y + height {
+            self.buffer.cursor_offset.y = pos.y + 1 - height;
+        }
+
+        if self.soft_wrap {
+            // Wrapped lines take several rows, so the cursor's row may still
+            // be below the view.
+            self.buffer.cursor_offset.x = 0;
+            let mut rows = text::wrap_row(&self.wrap_points(pos.y), pos.x)
+                + (self.buffer.cursor_offset.y..pos.y)
+                    .map(|y| self.wrap_points(y).len())
+                    .sum::<usize>();
+            while rows >= height && self.buffer.cursor_offset.y < pos.y {
+                rows -= self.wrap_points(self.buffer.cursor_offset.y).len();
+                self.buffer.cursor_offset.y += 1;
+            }
+        } else if column < self.buffer.cursor_offset.x {
+            self.buffer.cursor_offset.x = column;
+        } else if column >= self.buffer.cursor_offset.x + width {
+            self.buffer.cursor_offset.x = column + 1 - width;
+        }
+
+        self.buffer.cursor_position = Position {
+            x: column - self.buffer.cursor_offset.x,
+            y: pos.y - self.buffer.cursor_offset.y,
+        };
+    }
+
+    pub fn move_cursor(&mut self, direction: Direction, is_selection: bool) {
+        self.reset_quit();
+
+        if self.is_prompt {
+            if self.prompt_mode == PromptMode::Search && direction.y != 0 {
+                self.search_step(direction.y < 0);
+            }
+            if direction.x < 0 {
+                self.buffer.cursor_position.x =
+                    text::previous_grapheme(&self.prompt, self.buffer.cursor_position.x);
+            } else if direction.x > 0 {
+                self.buffer.cursor_position.x =
+                    text::next_grapheme(&self.prompt, self.buffer.cursor_position.x);
+            }
+            return;
+        }
+
+        self.update_selection(is_selection);
+
+        self.reset_status();
+
+        let pos = self.get_cursor_position();
+        let line = self.buffer.document.line(pos.y);
+
+        let target = if direction.x < 0 {
+            if pos.x > 0 {
+                Position {
+                    x: text::previous_grapheme(&line, pos.x),
+                    y: pos.y,
+                }
+            } else if pos.y > 0 {
+                Position {
+                    x: self.buffer.document.line_len(pos.y - 1),
+                    y: pos.y - 1,
+                }
+            } else {
+                pos
+            }
+        } else if direction.x > 0 {
+            if pos.x < self.buffer.document.line_len(pos.y) {
+                Position {
+                    x: text::next_grapheme(&line, pos.x),
+                    y: pos.y,
+                }
+            } else if pos.y + 1 < self.buffer.document.len_lines() {
+                Position { x: 0, y: pos.y + 1 }
+            } else {
+                pos
+            }
+        } else if self.soft_wrap && direction.y != 0 {
+            self.position_on_next_row(pos, direction.y > 0)
+        } else if direction.y > 0 && pos.y > 0 {
+            self.position_at_column(pos.y - 1, self.cursor_column())
+        } else if direction.y < 0 && pos.y + 1 < self.buffer.document.len_lines() {
+            self.position_at_column(pos.y + 1, self.cursor_column())
+        } else {
+            pos
+        };
+
+        self.set_cursor(target);
+    }
+
+    /// Position one screen row above or below `pos` with soft wrap on,
+    /// keeping the column within the row.
+    fn position_on_next_row(&self, pos: Position, up: bool) -> Position {
+        let line = self.buffer.document.line(pos.y);
+        let points = self.wrap_points(pos.y);
+        let row = text::wrap_row(&points, pos.x);
+        let column = text::column_of(&line, pos.x, self.tab_width)
+            - text::column_of(&line, points[row], self.tab_width);
+
+        let (y, row) = if up {
+            if row > 0 {
+                (pos.y, row - 1)
+            } else if pos.y > 0 {
+                (pos.y - 1, self.wrap_points(pos.y - 1).len() - 1)
+            } else {
+                return pos;
+            }
+        } else if row + 1 < points.len() {
+            (pos.y, row + 1)
+        } else if pos.y + 1 < self.buffer.document.len_lines() {
+            (pos.y + 1, 0)
+        } else {
+            return pos;
+        };
+
+        let line = self.buffer.document.line(y);
+        let points = self.wrap_points(y);
+        let start = text::column_of(&line, points[row], self.tab_width);
+        let mut x = text::index_at_column(&line, start + column, self.tab_width);
+        // Past the end of a row is the start of the next one.
+        if let Some(&next) = points.get(row + 1) {
+            if x >= next {
+                x = text::previous_grapheme(&line, next);
+            }
+        }
+        Position { x, y }
+    }
+
+    /// Character indices where the screen rows of line `y` start. Without
+    /// soft wrap every line is a single row.
+    pub fn wrap_points(&self, y: usize) -> Vec<usize> {
+        if !self.soft_wrap {
+            return vec![0];
+        }
+        text::wrap_points(
+            &self.buffer.document.line(y),
+            self.view_width(),
+            self.tab_width,
+            self.wrap_at_words,
+        )
+    }
+
+    /// Screen cell of the cursor within the text area.
+    pub fn cursor_cell(&self) -> Position {
+        if !self.soft_wrap {
+            return self.buffer.cursor_position;
+        }
+
+        let pos = self.get_cursor_position();
+        let line = self.buffer.document.line(pos.y);
+        let points = self.wrap_points(pos.y);
+        let row = text::wrap_row(&points, pos.x);
+        let rows_above: usize = (self.buffer.cursor_offset.y..pos.y)
+            .map(|y| self.wrap_points(y).len())
+            .sum();
+
+        Position {
+            x: min(
+                self.cursor_column() - text::column_of(&line, points[row], self.tab_width),
+                self.view_width() - 1,
+            ),
+            y: rows_above + row,
+        }
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.set_wrap(!self.soft_wrap);
+    }
+
+    fn set_wrap(&mut self, soft_wrap: bool) {
+        if self.is_prompt {
+            return;
+        }
+        let pos = self.get_cursor_position();
+        self.soft_wrap = soft_wrap;
+        self.buffer.cursor_offset.x = 0;
+        self.set_cursor(pos);
+        self.status = if soft_wrap {
+            "Soft wrap on".into()
+        } else {
+            "Soft wrap off".into()
+        };
+    }
+
+    fn position_at_column(&self, y: usize, column: usize) -> Position {
+        Position {
+            x: text::index_at_column(&self.buffer.document.line(y), column, self.tab_width),
+            y,
+        }
+    }
+
+    pub fn jump_at_end_line(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let pos = self.get_cursor_position();
+        self.set_cursor(Position {
+            x: self.buffer.document.line_len(pos.y),
+            y: pos.y,
+        });
+    }
+
+    pub fn jump_at_start_line(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let pos = self.get_cursor_position();
+        self.set_cursor(Position { x: 0, y: pos.y });
+    }
+
+    /// Goes to the first non-blank character, or to column 0 when already
+    /// there (or when smart home is off).
+    pub fn jump_at_line_home(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let pos = self.get_cursor_position();
+        let indent = self
+            .buffer
+            .document
+            .line(pos.y)
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        let x = if self.smart_home && pos.x != indent {
+            indent
+        } else {
+            0
+        };
+        self.set_cursor(Position { x, y: pos.y });
+    }
+
+    pub fn jump_at_start(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+        self.set_cursor(Position { x: 0, y: 0 });
+    }
+
+    pub fn jump_at_end(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let y = self.buffer.document.len_lines() - 1;
+        self.set_cursor(Position {
+            x: self.buffer.document.line_len(y),
+            y,
+        });
+    }
+
+    pub fn page_up(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let column = self.cursor_column();
+        let y = if self.buffer.cursor_offset.y == 0 {
+            0
+        } else {
+            self.buffer.cursor_offset.y = self
+                .buffer
+                .cursor_offset
+                .y
+                .saturating_sub(self.view_height());
+            self.buffer.cursor_offset.y + self.buffer.cursor_position.y
+        };
+        self.set_cursor(self.position_at_column(y, column));
+    }
+
+    pub fn page_down(&mut self, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let column = self.cursor_column();
+        let height = self.view_height();
+        let last = self.buffer.document.len_lines() - 1;
+        let max_offset = last.saturating_sub(height - 1);
+
+        let y = if self.buffer.cursor_offset.y >= max_offset {
+            last
+        } else {
+            self.buffer.cursor_offset.y = min(self.buffer.cursor_offset.y + height, max_offset);
+            min(
+                self.buffer.cursor_offset.y + self.buffer.cursor_position.y,
+                last,
+            )
+        };
+        self.set_cursor(self.position_at_column(y, column));
+    }
+
+    pub fn move_word(&mut self, forward: bool, is_selection: bool) {
+        if self.is_prompt {
+            return;
+        }
+        self.update_selection(is_selection);
+
+        let target = self.word_boundary(forward);
+        self.set_cursor(target);
+    }
+
+    pub fn delete_word(&mut self, forward: bool) {
+        if self.is_prompt {
+            if !forward {
+                self.pop_char();
+            }
+            return;
+        }
+
+        self.buffer.history.begin(self.cursor_state());
+        if !self.delete_selection() {
+            let pos = self.get_cursor_position();
+            let target = self.word_boundary(forward);
+            let (start, end) = if forward {
+                (pos, target)
+            } else {
+                (target, pos)
+            };
+            self.remove_text(start, end);
+            self.set_cursor(start);
+        }
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+    }
+
+    /// Next word boundary from the cursor, crossing into the neighbouring
+    /// line at either end of the current one.
+    fn word_boundary(&self, forward: bool) -> Position {
+        let pos = self.get_cursor_position();
+        let line = self.buffer.document.line(pos.y);
+
+        if forward {
+            match word::next_boundary(&line, pos.x) {
+                Some(x) => Position { x, y: pos.y },
+                None if pos.y + 1 < self.buffer.document.len_lines() => {
+                    Position { x: 0, y: pos.y + 1 }
+                }
+                None => pos,
+            }
+        } else {
+            match word::previous_boundary(&line, pos.x) {
+                Some(x) => Position { x, y: pos.y },
+                None if pos.y > 0 => Position {
+                    x: self.buffer.document.line_len(pos.y - 1),
+                    y: pos.y - 1,
+                },
+                None => pos,
+            }
+        }
+    }
+
+    fn update_selection(&mut self, is_selection: bool) {
+        if !is_selection {
+            self.buffer.is_selecting = false;
+        } else if !self.buffer.is_selecting {
+            self.buffer.is_selecting = true;
+            self.buffer.selecting_position = self.get_cursor_position();
+        }
+    }
+
+    pub fn selection(&self) -> Option<(Position, Position)> {
+        if !self.buffer.is_selecting {
+            return None;
+        }
+
+        let anchor = self.buffer.selecting_position;
+        let cursor = self.get_cursor_position();
+
+        match (anchor.y, anchor.x).cmp(&(cursor.y, cursor.x)) {
+            Ordering::Less => Some((anchor, cursor)),
+            Ordering::Greater => Some((cursor, anchor)),
+            Ordering::Equal => None,
+        }
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.buffer.is_selecting = false;
+
+        let Some((start, end)) = selection else {
+            return false;
+        };
+
+        self.remove_text(start, end);
+        self.set_cursor(start);
+        true
+    }
+
+    pub fn copy(&mut self) -> AppResult<()> {
+        let Some((start, end)) = self.selection() else {
+            self.status = "Nothing selected".into();
+            return Ok(());
+        };
+
+        let text = self.text_in(start, end);
+        self.clipboard.set(&text)?;
+        self.status = format!("Copied {} characters", text.chars().count());
+        Ok(())
+    }
+
+    pub fn cut(&mut self) -> AppResult<()> {
+        if self.is_prompt || self.selection().is_none() {
+            return self.copy();
+        }
+
+        self.copy()?;
+        self.buffer.history.begin(self.cursor_state());
+        self.delete_selection();
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+        Ok(())
+    }
+
+    pub fn paste(&mut self) -> AppResult<()> {
+        let text = self.clipboard.get()?;
+        self.insert_text(&text);
+        Ok(())
+    }
+
+    pub fn insert_text(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+        if self.is_prompt {
+            for c in text.lines().next().unwrap_or_default().chars() {
+                self.insert_char(c);
+            }
+            return;
+        }
+
+        if text.is_empty() {
+            return;
+        }
+
+        self.buffer.history.begin(self.cursor_state());
+        self.delete_selection();
+        let pos = self.get_cursor_position();
+        let end = self.insert_text_at(pos, &text);
+        self.set_cursor(end);
+        self.buffer.history.end(self.cursor_state());
+        self.after_edit();
+    }
+
+    pub fn start_search(&mut self) {
+        if self.is_prompt {
+            return;
+        }
+
+        self.search_origin = self.cursor_state();
+        self.buffer.is_selecting = false;
+        self.search_matches.clear();
+        self.search_index = None;
+        self.enter_prompt(PromptMode::Search);
+    }
+
+    pub fn search_next(&mut self) {
+        self.search_step(true);
+    }
+
+    pub fn search_previous(&mut self) {
+        self.search_step(false);
+    }
+
+    pub fn search_status(&self) -> String {
+        match self.search_index {
+            Some(i) => format!("{}/{}", i + 1, self.search_matches.len()),
+            None if self.search_query.is_empty() => String::new(),
+            None => format!("No matches for \"{}\"", self.search_query),
+        }
+    }
+
+    fn update_matches(&mut self) {
+        self.search_matches = self
+            .buffer
+            .document
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                search::find_in_line(&line, &self.search_query)
+                    .into_iter()
+                    .map(move |x| Position { x, y })
+            })
+            .collect();
+        self.search_index = None;
+    }
+
+    fn update_search(&mut self) {
+        self.search_query = self.prompt.clone();
+        self.update_matches();
+
+        if self.search_matches.is_empty() {
+            let caret = self.buffer.cursor_position;
+            self.restore_cursor(self.search_origin);
+            self.prompt_cursor_position = self.buffer.cursor_position;
+            self.buffer.cursor_position = caret;
+            return;
+        }
+
+        // The saved cursor is in display columns; matches are character
+        // indices.
+        let y = self.search_origin.position.y + self.search_origin.offset.y;
+        let origin = Position {
+            x: text::index_at_column(
+                &self.buffer.document.line(y),
+                self.search_origin.position.x + self.search_origin.offset.x,
+                self.tab_width,
+            ),
+            y,
+        };
+        let index = self
+            .search_matches
+            .partition_point(|m| (m.y, m.x) < (origin.y, origin.x));
+        self.select_match(if index == self.search_matches.len() {
+            0
+        } else {
+            index
+        });
+    }
+
+    fn search_step(&mut self, forward: bool) {
+        if self.search_matches.is_empty() {
+            self.update_matches();
+        }
+        if self.search_matches.is_empty() {
+            self.status = self.search_status();
+            return;
+        }
+
+        let pos = self.get_cursor_position();
+        let len = self.search_matches.len();
+        let index = if forward {
+            let i = self
+                .search_matches
+                .partition_point(|m| (m.y, m.x) <= (pos.y, pos.x));
+            if i == len {
+                0
+            } else {
+                i
+            }
+        } else {
+            let i = self
+                .search_matches
+                .partition_point(|m| (m.y, m.x) < (pos.y, pos.x));
+            if i == 0 {
+                len - 1
+            } else {
+                i - 1
+            }
+        };
+
+        self.select_match(index);
+    }
+
+    fn select_match(&mut self, index: usize) {
+        self.search_index = Some(index);
+        self.jump_to(self.search_matches[index]);
+        self.status = self.search_status();
+    }
+
+    /// Moves the document cursor, also while the prompt owns `cursor_position`.
+    fn jump_to(&mut self, pos: Position) {
+        if self.is_prompt {
+            let caret = self.buffer.cursor_position;
+            self.set_cursor(pos);
+            self.prompt_cursor_position = self.buffer.cursor_position;
+            self.buffer.cursor_position = caret;
+        } else {
+            self.set_cursor(pos);
+        }
+    }
+
+    pub fn start_replace(&mut self) {
+        if self.is_prompt {
+            return;
+        }
+
+        let mut replace = Replace::default();
+
+        if let Some((start, end)) = self.selection() {
+            replace.next = self.buffer.document.byte_index(start);
+            replace.end_from_end =
+                self.buffer.document.len_bytes() - self.buffer.document.byte_index(end);
+        }
+
+        self.buffer.is_selecting = false;
+        self.search_matches.clear();
+        self.search_index = None;
+        self.replace = Some(replace);
+        self.enter_prompt(PromptMode::ReplaceFind);
+    }
+
+    pub fn toggle_replace_regex(&mut self) {
+        if let (true, PromptMode::ReplaceFind, Some(replace)) =
+            (self.is_prompt, self.prompt_mode, &mut self.replace)
+        {
+            replace.is_regex = !replace.is_regex;
+        }
+    }
+
+    fn next_replace(&mut self) {
+        let text = self.full_text();
+        let Some(replace) = &mut self.replace else {
+            return;
+        };
+        let Some(replacer) = &replace.replacer else {
+            return;
+        };
+
+        let limit = text.len().saturating_sub(replace.end_from_end);
+        replace.current = if replace.next <= limit {
+            replacer.find_at(&text, replace.next, limit)
+        } else {
+            None
+        };
+
+        let Some((range, _)) = &replace.current else {
+            self.exit_prompt();
+            self.finish_replace();
+            return;
+        };
+
+        let start = self.buffer.document.position_of_byte(range.start);
+        let end = self.buffer.document.position_of_byte(range.end);
+        self.jump_to(end);
+        self.buffer.selecting_position = start;
+        self.buffer.is_selecting = true;
+    }
+
+    fn answer_replace(&mut self, answer: char) {
+        let text = self.full_text();
+        let Some(replace) = &mut self.replace else {
+            return;
+        };
+        let Some((range, replacement)) = replace.current.take() else {
+            return;
+        };
+
+        match answer {
+            'y' | 'Y' => {
+                // Continue right after the inserted text, in the new document.
+                let skipped = search::skip_match(&text, &range) - range.end;
+                replace.next = range.start + replacement.len() + skipped;
+                replace.count += 1;
+
+                self.exit_prompt();
+                self.buffer.history.begin(self.cursor_state());
+                self.replace_range(range, &replacement);
+                self.buffer.history.end(self.cursor_state());
+                self.after_edit();
+                self.enter_prompt(PromptMode::ReplaceConfirm);
+                self.next_replace();
+            }
+            'n' | 'N' => {
+                replace.next = search::skip_match(&text, &range);
+                self.next_replace();
+            }
+            'a' | 'A' => {
+                let limit = text.len().saturating_sub(replace.end_from_end);
+                let matches = match &replace.replacer {
+                    Some(replacer) => replacer.find_all(&text, range.start, limit),
+                    None => Vec::new(),
+                };
+                replace.count += matches.len();
+
+                self.exit_prompt();
+                self.buffer.history.begin(self.cursor_state());
+                for (range, replacement) in matches.into_iter().rev() {
+                    self.replace_range(range, &replacement);
+                }
+                self.buffer.history.end(self.cursor_state());
+                self.after_edit();
+                self.finish_replace();
+            }
+            'q' | 'Q' => {
+                self.exit_prompt();
+                self.finish_replace();
+            }
+            _ => replace.current = Some((range, replacement)),
+        }
+    }
+
+    fn replace_range(&mut self, range: Range<usize>, replacement: &str) {
+        let start = self.buffer.document.position_of_byte(range.start);
+        let end = self.buffer.document.position_of_byte(range.end);
+
+        self.remove_text(start, end);
+        let end = self.insert_text_at(start, replacement);
+        self.set_cursor(end);
+    }
+
+    fn finish_replace(&mut self) {
+        self.buffer.is_selecting = false;
+        if let Some(replace) = self.replace.take() {
+            self.status = match replace.count {
+                1 => "Replaced 1 occurrence".into(),
+                n => format!("Replaced {} occurrences", n),
+            };
+        }
+    }
+
+    pub fn start_goto(&mut self) {
+        if !self.is_prompt {
+            self.enter_prompt(PromptMode::GotoLine);
+        }
+    }
+
+    fn goto(&mut self, input: &str) {
+        let pos = self.get_cursor_position();
+
+        let Some(target) = goto::parse(input, pos.y, self.buffer.document.len_lines()) else {
+            self.status = format!("Invalid line: {}", input);
+            return;
+        };
+
+        // The column is the display column the status bar shows.
+        let x = target.column.map_or(0, |column| {
+            text::index_at_column(
+                &self.buffer.document.line(target.line),
+                column,
+                self.tab_width,
+            )
+        });
+        self.status = self.default_status();
+        self.buffer.is_selecting = false;
+        self.center_on(Position { x, y: target.line });
+    }
+
+    /// Moves the cursor to `pos`, scrolling so that its line is mid-screen.
+    fn center_on(&mut self, pos: Position) {
+        self.buffer.cursor_offset.y = pos.y.saturating_sub(self.view_height() / 2);
+        self.set_cursor(pos);
+    }
+
+    pub fn start_command(&mut self) {
+        if !self.is_prompt {
+            self.enter_prompt(PromptMode::Command);
+        }
+    }
+
+    fn run_command(&mut self, input: &str) {
+        let mut words = input.split_whitespace();
+        match (words.next(), words.next()) {
+            (None, _) => {}
+            (Some("split"), None) => self.split_pane(SplitDirection::Vertical),
+            (Some("vsplit"), None) => self.split_pane(SplitDirection::Horizontal),
+            (Some("close"), None) => self.close_pane(),
+            (Some("focus"), Some(direction @ ("next" | "prev"))) => {
+                self.focus_next_pane(direction == "next")
+            }
+            (Some("resize"), Some(delta)) => match delta.parse::<i16>() {
+                Ok(delta) => self.resize_pane(delta),
+                Err(_) => self.status = format!("Not a number: {}", delta),
+            },
+            (Some("keys"), None) => self.show_bindings(),
+            (Some("wrap"), None) => self.toggle_wrap(),
+            (Some("wrap"), Some("on")) => self.set_wrap(true),
+            (Some("wrap"), Some("off")) => self.set_wrap(false),
+            (Some("wrap"), Some(mode @ ("words" | "chars"))) => {
+                self.wrap_at_words = mode == "words";
+                self.set_wrap(true);
+            }
+            (Some("eol"), None) => {
+                self.status = format!("Line endings: {}", self.buffer.format.line_ending);
+            }
+            (Some("eol"), Some(name)) => match LineEnding::parse(name) {
+                Some(line_ending) => self.set_line_ending(line_ending),
+                None => self.status = format!("Unknown line ending: {}", name),
+            },
+            (Some("encoding"), None) => {
+                self.status = format!("Encoding: {}", self.buffer.encoding);
+            }
+            (Some("encoding"), Some(name)) => match Encoding::parse(name) {
+                Some(encoding) => self.set_encoding(encoding),
+                None => self.status = format!("Unknown encoding: {}", name),
+            },
+            (Some("reopen"), Some(name)) => match Encoding::parse(name) {
+                Some(encoding) => {
+                    if let Err(err) = self.reopen(encoding) {
+                        self.show_error(&err);
+                    }
+                }
+                None => self.status = format!("Unknown encoding: {}", name),
+            },
+            (Some("bom"), Some(value @ ("on" | "off"))) => {
+                let bom = value == "on";
+                if self.buffer.encoding == Encoding::Latin1 && bom {
+                    self.status = "Latin-1 has no byte order mark".into();
+                } else if self.buffer.bom != bom {
+                    self.buffer.bom = bom;
+                    self.buffer.history.mark_unsaved();
+                    self.buffer.dirty = true;
+                }
+            }
+            (Some(command), _) => self.status = format!("Unknown command: {}", command),
+        }
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.buffer.format.line_ending != line_ending || self.buffer.format.mixed {
+            self.buffer.format.line_ending = line_ending;
+            self.buffer.format.mixed = false;
+            self.buffer.history.mark_unsaved();
+            self.buffer.dirty = true;
+        }
+        self.status = format!("Line endings: {}", line_ending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_with(text: &str) -> App {
+        let mut app = App::new();
+        app.window_size = Rect::new(0, 0, 80, 24);
+        app.buffer.document = Document::from(text);
+        app
+    }
+
+    #[test]
+    fn backspace_after_collapsed_selection_deletes_before_cursor() {
+        let mut app = app_with("hello world");
+        app.set_cursor(Position { x: 4, y: 0 });
+        app.move_cursor(Direction { x: -1, y: 0 }, true);
+        app.move_cursor(Direction { x: 1, y: 0 }, true);
+        assert_eq!(app.selection(), None);
+
+        app.pop_char();
+        assert_eq!(app.buffer.document.to_string(), "helo world");
+        assert!(!app.buffer.is_selecting);
+        assert_eq!(app.get_cursor_position(), Position { x: 3, y: 0 });
+
+        app.pop_char();
+        assert_eq!(app.buffer.document.to_string(), "heo world");
+    }
+
+    #[test]
+    fn search_starts_from_cursor_after_tabs() {
+        let mut app = app_with("\t\tab ab");
+        app.set_cursor(Position { x: 4, y: 0 });
+
+        app.start_search();
+        app.insert_char('a');
+        app.insert_char('b');
+        assert_eq!(app.search_index, Some(1));
+        assert_eq!(app.search_matches[1], Position { x: 5, y: 0 });
+    }
+
+    #[test]
+    fn status_hints_follow_the_keymap() {
+        let mut app = app_with("");
+        assert_eq!(app.status, "Press Ctrl+Q to quit, Ctrl+S to save.");
+
+        let config: Config = toml::from_str(
+            "[keys]\n\"Ctrl+Q\" = \"none\"\n\"Ctrl+X Ctrl+C\" = \"quit\"\n\"Ctrl+W\" = \"none\"",
+        )
+        .unwrap();
+        app.configure(&config);
+        assert_eq!(app.status, "Press Ctrl+X Ctrl+C to quit, Ctrl+S to save.");
+
+        app.buffer.dirty = true;
+        app.quit();
+        assert!(app.running);
+        assert!(
+            app.status.ends_with("Press Ctrl+X Ctrl+C again to quit."),
+            "{}",
+            app.status
+        );
+
+        app.close_buffer();
+        assert!(
+            app.status.ends_with("has unsaved changes."),
+            "{}",
+            app.status
+        );
+    }
+
+    #[test]
+    fn smart_indent_can_be_turned_off() {
+        for (smart_indent, expected) in [
+            (true, "    if x {\n    \t\n    }"),
+            (false, "    if x {\n    }"),
+        ] {
+            let mut app = app_with("    if x {}");
+            app.smart_indent = smart_indent;
+            app.set_cursor(Position { x: 10, y: 0 });
+            app.add_new_line();
+            assert_eq!(app.buffer.document.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn goto_column_matches_the_status_bar() {
+        let mut app = app_with("\tab");
+        app.goto("1:6");
+        assert_eq!(app.get_cursor_position(), Position { x: 2, y: 0 });
+        assert_eq!(app.cursor_column() + 1, 6);
+
+        app.goto("1:3");
+        assert_eq!(app.get_cursor_position(), Position { x: 0, y: 0 });
+        app.goto("1:99");
+        assert_eq!(app.get_cursor_position(), Position { x: 3, y: 0 });
+    }
+
+    #[test]
+    fn word_motion_crosses_line_ends() {
+        let mut app = app_with("a->b\n  c");
+        let mut stops = Vec::new();
+        for _ in 0..6 {
+            app.move_word(true, false);
+            stops.push(app.get_cursor_position());
+        }
+        let expected = [(1, 0), (3, 0), (4, 0), (0, 1), (3, 1), (3, 1)];
+        assert_eq!(stops, expected.map(|(x, y)| Position { x, y }));
+
+        app.move_word(false, false);
+        assert_eq!(app.get_cursor_position(), Position { x: 2, y: 1 });
+        app.move_word(false, false);
+        assert_eq!(app.get_cursor_position(), Position { x: 0, y: 1 });
+        app.move_word(false, false);
+        assert_eq!(app.get_cursor_position(), Position { x: 4, y: 0 });
+    }
+
+    #[test]
+    fn replace_rejects_an_empty_pattern() {
+        let mut app = app_with("abc");
+        app.start_replace();
+        app.submit_prompt();
+
+        assert!(!app.is_prompt);
+        assert!(app.replace.is_none());
+        assert_eq!(app.status, "Nothing to replace");
+        assert_eq!(app.buffer.document.to_string(), "abc");
+    }
+
+    #[test]
+    fn open_buffer_recognises_other_spellings_of_a_path() {
+        let dir = std::env::temp_dir().join(format!("rust-edit-open-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+
+        let mut app = App::new();
+        app.open_buffer(&path("a.txt")).unwrap();
+        app.open_buffer(&path("b.txt")).unwrap();
+        app.open_buffer(&path("./a.txt")).unwrap();
+        assert_eq!(app.buffers.len(), 2);
+        assert_eq!(app.buffer.document.to_string(), "a");
+        app.open_buffer(&path("sub/../b.txt")).unwrap();
+        assert_eq!(app.buffers.len(), 2);
+        assert_eq!(app.buffer.document.to_string(), "b");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backup_is_written_once_per_buffer() {
+        let dir = std::env::temp_dir().join(format!("rust-edit-backup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.txt");
+        fs::write(&path, "original").unwrap();
+
+        let mut app = App::new();
+        app.backup = true;
+        app.open_buffer(&path.display().to_string()).unwrap();
+        for text in ["first", "second"] {
+            app.buffer.document = Document::from(text);
+            app.save_to_file().unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(
+            fs::read_to_string(file::backup_path(&path)).unwrap(),
+            "original"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use crate::{
+    app::Position, document::Document, encoding::Encoding, history::History, line_ending::Format,
+};
+
+/// An open file with its own cursor, scroll position and undo history.
+#[derive(Debug)]
+pub struct Buffer {
+    pub document: Document,
+    pub opened_filename: String,
+    pub cursor_position: Position,
+    pub cursor_offset: Position,
+    pub dirty: bool,
+    pub history: History,
+    pub backup_written: bool,
+    pub is_selecting: bool,
+    pub selecting_position: Position,
+    pub format: Format,
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self {
+            document: Document::new(),
+            opened_filename: String::new(),
+            cursor_position: Position { x: 0, y: 0 },
+            cursor_offset: Position { x: 0, y: 0 },
+            dirty: false,
+            history: History::default(),
+            backup_written: false,
+            is_selecting: false,
+            selecting_position: Position { x: 0, y: 0 },
+            format: Format::default(),
+            encoding: Encoding::Utf8,
+            bom: false,
+        }
+    }
+}
+
+impl Buffer {
+    /// File name shown in the tab line.
+    pub fn name(&self) -> &str {
+        if self.opened_filename.is_empty() {
+            return "[No Name]";
+        }
+        Path::new(&self.opened_filename)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.opened_filename)
+    }
+
+    /// An empty buffer that was never saved or edited, which opening a file
+    /// can take over.
+    pub fn is_scratch(&self) -> bool {
+        self.opened_filename.is_empty()
+            && !self.dirty
+            && self.document.len_lines() == 1
+            && self.document.line_len(0) == 0
+    }
+}
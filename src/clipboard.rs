@@ -0,0 +1,238 @@
+use std::{
+    env, fmt,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use serde::Deserialize;
+
+use crate::app::AppResult;
+
+pub trait Clipboard: fmt::Debug {
+    fn set(&mut self, text: &str) -> AppResult<()>;
+    fn get(&mut self) -> AppResult<String>;
+}
+
+/// Programs to copy and paste with, from the `[clipboard]` table of the user
+/// config. Project files may not set them. Arguments are split on whitespace,
+/// without shell quoting.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Commands {
+    pub copy: String,
+    pub paste: String,
+}
+
+/// Uses the configured commands if there are any, otherwise picks the best
+/// clipboard available in the current environment.
+pub fn detect(commands: Option<&Commands>) -> Box<dyn Clipboard> {
+    if let Some(commands) = commands {
+        Box::new(CommandClipboard::new(&commands.copy, &commands.paste))
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() && in_path("wl-copy") {
+        Box::new(CommandClipboard::wayland())
+    } else if env::var_os("DISPLAY").is_some() && in_path("xclip") {
+        Box::new(CommandClipboard::xclip())
+    } else if io::stderr().is_terminal() {
+        Box::new(Osc52::default())
+    } else {
+        Box::new(Register::default())
+    }
+}
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+#[derive(Debug, Default)]
+pub struct Register {
+    text: String,
+}
+
+impl Clipboard for Register {
+    fn set(&mut self, text: &str) -> AppResult<()> {
+        self.text = text.into();
+        Ok(())
+    }
+
+    fn get(&mut self) -> AppResult<String> {
+        Ok(self.text.clone())
+    }
+}
+
+/// Sends copied text to the terminal's clipboard with the OSC 52 escape
+/// sequence. Terminals rarely allow reading it back, so pasting uses the
+/// last copied text.
+#[derive(Debug, Default)]
+pub struct Osc52 {
+    register: Register,
+}
+
+impl Clipboard for Osc52 {
+    fn set(&mut self, text: &str) -> AppResult<()> {
+        let mut stderr = io::stderr();
+        write!(stderr, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+        stderr.flush()?;
+        self.register.set(text)
+    }
+
+    fn get(&mut self) -> AppResult<String> {
+        self.register.get()
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandClipboard {
+    copy: Vec<String>,
+    paste: Vec<String>,
+}
+
+impl CommandClipboard {
+    pub fn new(copy: &str, paste: &str) -> Self {
+        Self {
+            copy: copy.split_whitespace().map(String::from).collect(),
+            paste: paste.split_whitespace().map(String::from).collect(),
+        }
+    }
+
+    pub fn xclip() -> Self {
+        Self::new(
+            "xclip -selection clipboard -in",
+            "xclip -selection clipboard -out",
+        )
+    }
+
+    pub fn wayland() -> Self {
+        Self::new("wl-copy", "wl-paste --no-newline")
+    }
+
+    fn command(args: &[String]) -> io::Result<Command> {
+        let (program, args) = args.split_first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "empty clipboard command")
+        })?;
+        let mut command = Command::new(program);
+        command.args(args).stderr(Stdio::null());
+        Ok(command)
+    }
+}
+
+impl Clipboard for CommandClipboard {
+    fn set(&mut self, text: &str) -> AppResult<()> {
+        let mut child = Self::command(&self.copy)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(format!("{} exited with {}", self.copy[0], status).into());
+        }
+        Ok(())
+    }
+
+    fn get(&mut self) -> AppResult<String> {
+        let output = Self::command(&self.paste)?.output()?;
+        if !output.status.success() {
+            return Err(format!("{} exited with {}", self.paste[0], output.status).into());
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_rfc_4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in vectors {
+            assert_eq!(base64(input.as_bytes()), expected);
+        }
+    }
+
+    #[cfg(unix)]
+    fn script(dir: &std::path::Path, name: &str, body: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.display().to_string()
+    }
+
+    #[cfg(unix)]
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = env::temp_dir().join(format!("rust-edit-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_clipboard_round_trips_through_scripts() {
+        let dir = temp_dir("clipboard");
+        let store = dir.join("store");
+        let copy = script(&dir, "copy", "cat > \"$1\"");
+        let paste = script(&dir, "paste", "cat \"$1\"");
+
+        let mut clipboard = CommandClipboard::new(
+            &format!("{} {}", copy, store.display()),
+            &format!("{} {}", paste, store.display()),
+        );
+        clipboard.set("first line\nsecond ✓").unwrap();
+        assert_eq!(clipboard.get().unwrap(), "first line\nsecond ✓");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_clipboard_reports_failing_commands() {
+        let dir = temp_dir("clipboard-fail");
+        let fail = script(&dir, "fail", "cat > /dev/null; exit 3");
+
+        let mut clipboard = CommandClipboard::new(&fail, &fail);
+        let err = clipboard.set("text").unwrap_err();
+        assert!(err.to_string().contains("exited with"), "{}", err);
+        let err = clipboard.get().unwrap_err();
+        assert!(err.to_string().contains("exited with"), "{}", err);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    env,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{de, Deserialize, Deserializer};
+use toml::{Table, Value};
+
+use crate::{clipboard, error::ConfigError, keymap::Bindings, theme::Theme};
+
+/// Per-project overrides, looked up from the working directory upwards.
+pub const PROJECT_FILE: &str = ".rust-edit.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbers {
+    Absolute,
+    Relative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Wrap {
+    Off,
+    Words,
+    Chars,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub line_numbers: LineNumbers,
+    #[serde(deserialize_with = "tab_width")]
+    pub tab_width: usize,
+    pub soft_tabs: bool,
+    pub auto_indent: bool,
+    /// Indent a level after an opening bracket or colon, and dedent closing
+    /// brackets, on top of carrying over the previous line's indentation.
+    pub smart_indent: bool,
+    pub smart_home: bool,
+    pub backup: bool,
+    /// Presses of the quit key needed to quit with unsaved changes.
+    #[serde(deserialize_with = "at_least_one")]
+    pub quit_times: u8,
+    /// Milliseconds between ticks.
+    #[serde(deserialize_with = "at_least_one")]
+    pub tick_rate: u64,
+    pub wrap: Wrap,
+    pub theme: Theme,
+    pub keys: Bindings,
+    pub clipboard: Option<clipboard::Commands>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            line_numbers: LineNumbers::Relative,
+            tab_width: 4,
+            soft_tabs: false,
+            auto_indent: true,
+            smart_indent: true,
+            smart_home: true,
+            backup: false,
+            quit_times: 2,
+            tick_rate: 250,
+            wrap: Wrap::Off,
+            theme: Theme::default(),
+            keys: Bindings::default(),
+            clipboard: None,
+        }
+    }
+}
+
+fn tab_width<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let width = usize::deserialize(deserializer)?;
+    if !(1..=16).contains(&width) {
+        return Err(de::Error::custom("tab_width must be between 1 and 16"));
+    }
+    Ok(width)
+}
+
+fn at_least_one<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + PartialOrd + From<u8> + Display,
+{
+    let value = T::deserialize(deserializer)?;
+    if value < T::from(1) {
+        return Err(de::Error::custom(format!(
+            "expected a value of at least 1, found {}",
+            value
+        )));
+    }
+    Ok(value)
+}
+
+/// `$XDG_CONFIG_HOME/rust-edit/config.toml`, with `XDG_CONFIG_HOME`
+/// defaulting to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("rust-edit").join("config.toml"))
+}
+
+pub fn project_path(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Reads the user config from `path`, or from the default location if it
+/// exists, and layers the project file found from `dir` on top of it.
+pub fn load(path: Option<&Path>, dir: &Path) -> Result<Config, ConfigError> {
+    let user = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => default_path().filter(|path| path.is_file()),
+    };
+
+    let mut table = Table::new();
+    if let Some(path) = user {
+        merge(&mut table, read(&path, false)?);
+    }
+    if let Some(path) = project_path(dir) {
+        merge(&mut table, read(&path, true)?);
+    }
+
+    Value::Table(table)
+        .try_into()
+        .map_err(|source| ConfigError::Parse {
+            path: "configuration".into(),
+            source,
+        })
+}
+
+fn read(path: &Path, project: bool) -> Result<Table, ConfigError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: name.clone(),
+        source,
+    })?;
+
+    // Checked on its own so errors point at a line in this file rather than
+    // at the merged result.
+    toml::from_str::<Config>(&text)
+        .and_then(|_| {
+            if project {
+                toml::from_str::<ProjectRules>(&text)?;
+            }
+            toml::from_str::<Table>(&text)
+        })
+        .map_err(|source| ConfigError::Parse { path: name, source })
+}
+
+/// Checks what a project file may not set. Any checked-out repository can
+/// bring one along, so it mustn't pick programs for the editor to run.
+#[derive(Deserialize)]
+struct ProjectRules {
+    #[serde(rename = "clipboard", default, deserialize_with = "user_only")]
+    _clipboard: (),
+}
+
+fn user_only<'de, D: Deserializer<'de>>(_: D) -> Result<(), D::Error> {
+    Err(de::Error::custom(
+        "clipboard commands can only be set in the user config",
+    ))
+}
+
+fn merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overrides)) => merge(base, overrides),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+
+    use super::*;
+
+    /// A fresh directory holding `files`, removed again by the caller.
+    fn dir_with(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rust-edit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn project_files_cannot_set_clipboard_commands() {
+        let dir = dir_with(
+            "config-clipboard",
+            &[
+                (
+                    "user.toml",
+                    "[clipboard]\ncopy = \"cat\"\npaste = \"cat\"\n",
+                ),
+                (
+                    PROJECT_FILE,
+                    "tab_width = 2\n\n[clipboard]\ncopy = \"a\"\npaste = \"b\"\n",
+                ),
+            ],
+        );
+
+        let err = load(Some(&dir.join("user.toml")), &dir).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(PROJECT_FILE), "{}", message);
+        assert!(message.contains("line 3"), "{}", message);
+        assert!(
+            message.contains("only be set in the user config"),
+            "{}",
+            message
+        );
+
+        fs::write(dir.join(PROJECT_FILE), "tab_width = 2\n").unwrap();
+        let config = load(Some(&dir.join("user.toml")), &dir).unwrap();
+        assert_eq!(
+            config.clipboard.map(|commands| commands.copy),
+            Some("cat".into())
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn project_settings_override_the_user_config() {
+        let dir = dir_with(
+            "config-merge",
+            &[
+                (
+                    "user.toml",
+                    "tab_width = 8\nsoft_tabs = true\n\n[theme]\nforeground = \"red\"\nbackground = \"blue\"\n",
+                ),
+                (
+                    PROJECT_FILE,
+                    "tab_width = 2\nwrap = \"words\"\n\n[theme]\nbackground = \"#102030\"\n",
+                ),
+            ],
+        );
+        let nested = dir.join("src").join("deeper");
+        fs::create_dir_all(&nested).unwrap();
+
+        let config = load(Some(&dir.join("user.toml")), &nested).unwrap();
+        assert_eq!(config.tab_width, 2);
+        assert!(config.soft_tabs);
+        assert_eq!(config.wrap, Wrap::Words);
+        assert_eq!(config.theme.foreground, Color::Red);
+        assert_eq!(config.theme.background, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(config.theme.status_bar, Theme::default().status_bar);
+        assert_eq!(config.quit_times, Config::default().quit_times);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn project_file_is_optional() {
+        let dir = dir_with("config-user-only", &[("user.toml", "backup = true\n")]);
+
+        let config = load(Some(&dir.join("user.toml")), &dir).unwrap();
+        assert!(config.backup);
+        assert_eq!(config.tab_width, Config::default().tab_width);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// The message for loading `contents` as the project file.
+    fn project_error(name: &str, contents: &str) -> String {
+        let dir = dir_with(name, &[("user.toml", ""), (PROJECT_FILE, contents)]);
+        let message = load(Some(&dir.join("user.toml")), &dir)
+            .unwrap_err()
+            .to_string();
+        fs::remove_dir_all(dir).unwrap();
+        message
+    }
+
+    #[test]
+    fn errors_point_at_the_line_in_their_file() {
+        let message = project_error("config-tab-width", "backup = true\ntab_width = 40\n");
+        assert!(message.contains(PROJECT_FILE), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+        assert!(
+            message.contains("tab_width must be between 1 and 16"),
+            "{}",
+            message
+        );
+
+        let message = project_error("config-unknown", "\n[theme]\nforegrond = \"red\"\n");
+        assert!(message.contains("line 3"), "{}", message);
+        assert!(message.contains("foregrond"), "{}", message);
+
+        let message = project_error("config-color", "[theme]\nforeground = \"nope\"\n");
+        assert!(message.contains("line 2"), "{}", message);
+        assert!(message.contains("invalid color `nope`"), "{}", message);
+
+        let message = project_error("config-keys", "[keys]\n\"Ctrl+Q\" = \"qiut\"\n");
+        assert!(message.contains("line 2"), "{}", message);
+        assert!(message.contains("unknown command `qiut`"), "{}", message);
+
+        let message = project_error("config-quit", "quit_times = 0\n");
+        assert!(message.contains("line 1"), "{}", message);
+        assert!(message.contains("at least 1"), "{}", message);
+    }
+
+    #[test]
+    fn missing_user_file_is_an_error() {
+        let dir = dir_with("config-missing", &[]);
+        let err = load(Some(&dir.join("nope.toml")), &dir).unwrap_err();
+        assert!(matches!(err, ConfigError::Io { .. }), "{}", err);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use ropey::Rope;
+
+use crate::app::Position;
+
+/// Text of a buffer kept in a rope, so edits and line lookups stay cheap on
+/// large files. Lines are split on `\n` only; the file's own line endings
+/// are put back when saving.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    rope: Rope,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    /// Line `y` without its line break.
+    pub fn line(&self, y: usize) -> String {
+        let mut line = self.rope.line(y).to_string();
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        line
+    }
+
+    /// Length of line `y` in characters, not counting the line break.
+    pub fn line_len(&self, y: usize) -> usize {
+        let line = self.rope.line(y);
+        let len = line.len_chars();
+        if len > 0 && line.char(len - 1) == '\n' {
+            len - 1
+        } else {
+            len
+        }
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len_lines()).map(|y| self.line(y))
+    }
+
+    pub fn char_index(&self, pos: Position) -> usize {
+        self.rope.line_to_char(pos.y) + pos.x
+    }
+
+    pub fn byte_index(&self, pos: Position) -> usize {
+        self.rope.char_to_byte(self.char_index(pos))
+    }
+
+    pub fn position(&self, char_index: usize) -> Position {
+        let y = self.rope.char_to_line(char_index);
+        Position {
+            x: char_index - self.rope.line_to_char(y),
+            y,
+        }
+    }
+
+    pub fn position_of_byte(&self, byte: usize) -> Position {
+        self.position(self.rope.byte_to_char(byte))
+    }
+
+    /// Inserts `text` and returns the position right after it.
+    pub fn insert(&mut self, at: Position, text: &str) -> Position {
+        let index = self.char_index(at);
+        self.rope.insert(index, text);
+        self.position(index + text.chars().count())
+    }
+
+    /// Removes the text between two positions and returns it.
+    pub fn remove(&mut self, start: Position, end: Position) -> String {
+        let range = self.char_index(start)..self.char_index(end);
+        let removed = self.rope.slice(range.clone()).to_string();
+        self.rope.remove(range);
+        removed
+    }
+
+    pub fn slice(&self, start: Position, end: Position) -> String {
+        self.rope
+            .slice(self.char_index(start)..self.char_index(end))
+            .to_string()
+    }
+}
+
+impl From<&str> for Document {
+    fn from(text: &str) -> Self {
+        Self {
+            rope: Rope::from_str(text),
+        }
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in self.rope.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: usize, y: usize) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn lines_exclude_the_line_break() {
+        let document = Document::from("ab\ncd\n");
+        assert_eq!(document.len_lines(), 3);
+        assert_eq!(document.lines().collect::<Vec<_>>(), ["ab", "cd", ""]);
+        assert_eq!(document.line_len(0), 2);
+        assert_eq!(document.line_len(2), 0);
+    }
+
+    #[test]
+    fn only_newlines_break_lines() {
+        let document = Document::from("a\rb\u{2028}c\nd");
+        assert_eq!(document.len_lines(), 2);
+        assert_eq!(document.line(0), "a\rb\u{2028}c");
+        assert_eq!(document.line_len(0), 5);
+    }
+
+    #[test]
+    fn empty_document_has_one_line() {
+        let document = Document::new();
+        assert_eq!(document.len_lines(), 1);
+        assert_eq!(document.line(0), "");
+        assert_eq!(document.line_len(0), 0);
+    }
+
+    #[test]
+    fn maps_positions_to_chars_and_bytes() {
+        let document = Document::from("héllo\nwörld");
+        assert_eq!(document.char_index(pos(0, 1)), 6);
+        assert_eq!(document.char_index(pos(2, 1)), 8);
+        assert_eq!(document.byte_index(pos(2, 0)), 3);
+        assert_eq!(document.byte_index(pos(2, 1)), 10);
+        assert_eq!(document.len_bytes(), 13);
+    }
+
+    #[test]
+    fn maps_chars_and_bytes_back_to_positions() {
+        let document = Document::from("héllo\nwörld");
+        assert_eq!(document.position(0), pos(0, 0));
+        assert_eq!(document.position(5), pos(5, 0));
+        assert_eq!(document.position(6), pos(0, 1));
+        assert_eq!(document.position(11), pos(5, 1));
+        assert_eq!(document.position_of_byte(3), pos(2, 0));
+        assert_eq!(document.position_of_byte(10), pos(2, 1));
+    }
+
+    #[test]
+    fn insert_returns_the_end_of_the_text() {
+        let mut document = Document::from("ac");
+        assert_eq!(document.insert(pos(1, 0), "b"), pos(2, 0));
+        assert_eq!(document.insert(pos(3, 0), "\nxé\ny"), pos(1, 2));
+        assert_eq!(document.to_string(), "abc\nxé\ny");
+    }
+
+    #[test]
+    fn remove_and_slice_across_lines() {
+        let mut document = Document::from("one\ntwo\nthree");
+        assert_eq!(document.slice(pos(1, 0), pos(2, 1)), "ne\ntw");
+        assert_eq!(document.remove(pos(1, 0), pos(2, 1)), "ne\ntw");
+        assert_eq!(document.to_string(), "oo\nthree");
+        assert_eq!(document.len_lines(), 2);
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Some(Encoding::Utf8),
+            "utf16le" | "utf16" => Some(Encoding::Utf16Le),
+            "utf16be" => Some(Encoding::Utf16Be),
+            "latin1" | "iso88591" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xef\xbb\xbf",
+            Encoding::Utf16Le => b"\xff\xfe",
+            Encoding::Utf16Be => b"\xfe\xff",
+            Encoding::Latin1 => b"",
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Utf16Le => write!(f, "UTF-16LE"),
+            Encoding::Utf16Be => write!(f, "UTF-16BE"),
+            Encoding::Latin1 => write!(f, "Latin-1"),
+        }
+    }
+}
+
+/// Text decoded from a file along with how to write it back.
+#[derive(Debug)]
+pub struct Decoded {
+    pub text: String,
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+/// Picks the encoding from a byte order mark, then tries UTF-8 and falls
+/// back to Latin-1, which maps every byte to a character and back unchanged.
+/// UTF-16 is only recognised by its byte order mark; without one it decodes
+/// as UTF-8 with NUL characters and can be reopened with an explicit encoding.
+pub fn detect(bytes: &[u8]) -> Decoded {
+    for encoding in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        if bytes.starts_with(encoding.bom()) {
+            if let Some(decoded) = decode(bytes, encoding) {
+                return decoded;
+            }
+        }
+    }
+
+    decode(bytes, Encoding::Utf8).unwrap_or_else(|| Decoded {
+        text: bytes.iter().map(|&b| b as char).collect(),
+        encoding: Encoding::Latin1,
+        bom: false,
+    })
+}
+
+/// Decodes `bytes` as `encoding`, skipping its byte order mark. Returns
+/// `None` when they aren't valid in that encoding.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Option<Decoded> {
+    let bom = !encoding.bom().is_empty() && bytes.starts_with(encoding.bom());
+    let body = if bom {
+        &bytes[encoding.bom().len()..]
+    } else {
+        bytes
+    };
+
+    let text = match encoding {
+        Encoding::Utf8 => String::from_utf8(body.to_vec()).ok()?,
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if body.len() % 2 != 0 {
+                return None;
+            }
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|pair| match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16(&units).ok()?
+        }
+        Encoding::Latin1 => body.iter().map(|&b| b as char).collect(),
+    };
+
+    Some(Decoded {
+        text,
+        encoding,
+        bom,
+    })
+}
+
+/// Encodes `text`, or returns the first character `encoding` can't represent.
+pub fn encode(text: &str, encoding: Encoding, bom: bool) -> Result<Vec<u8>, char> {
+    let mut bytes = Vec::with_capacity(text.len() + 3);
+    if bom {
+        bytes.extend_from_slice(encoding.bom());
+    }
+
+    match encoding {
+        Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+        Encoding::Utf16Le => text
+            .encode_utf16()
+            .for_each(|unit| bytes.extend_from_slice(&unit.to_le_bytes())),
+        Encoding::Utf16Be => text
+            .encode_utf16()
+            .for_each(|unit| bytes.extend_from_slice(&unit.to_be_bytes())),
+        Encoding::Latin1 => {
+            for c in text.chars() {
+                bytes.push(u8::try_from(c).map_err(|_| c)?);
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_with_bom() {
+        let decoded = detect(b"\xef\xbb\xbfcaf\xc3\xa9");
+        assert_eq!(decoded.text, "café");
+        assert_eq!(decoded.encoding, Encoding::Utf8);
+        assert!(decoded.bom);
+        assert_eq!(
+            encode(&decoded.text, decoded.encoding, decoded.bom).unwrap(),
+            b"\xef\xbb\xbfcaf\xc3\xa9"
+        );
+    }
+
+    #[test]
+    fn utf16_with_bom() {
+        for (bytes, encoding) in [
+            (&b"\xff\xfeh\x00\xe9\x00"[..], Encoding::Utf16Le),
+            (&b"\xfe\xff\x00h\x00\xe9"[..], Encoding::Utf16Be),
+        ] {
+            let decoded = detect(bytes);
+            assert_eq!(decoded.text, "hé");
+            assert_eq!(decoded.encoding, encoding);
+            assert!(decoded.bom);
+            assert_eq!(encode(&decoded.text, encoding, true).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn odd_length_utf16_is_invalid() {
+        assert!(decode(b"\xff\xfeh\x00i", Encoding::Utf16Le).is_none());
+        assert!(decode(b"\x00h\x00", Encoding::Utf16Be).is_none());
+    }
+
+    #[test]
+    fn invalid_utf8_falls_back_to_latin1() {
+        let bytes = b"na\xefve \xff\x80";
+        let decoded = detect(bytes);
+        assert_eq!(decoded.text, "na\u{ef}ve \u{ff}\u{80}");
+        assert_eq!(decoded.encoding, Encoding::Latin1);
+        assert!(!decoded.bom);
+        assert_eq!(
+            encode(&decoded.text, Encoding::Latin1, false).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn latin1_rejects_wider_characters() {
+        assert_eq!(
+            encode("caf\u{e9}", Encoding::Latin1, false),
+            Ok(b"caf\xe9".to_vec())
+        );
+        assert_eq!(
+            encode("a \u{20ac} b", Encoding::Latin1, false),
+            Err('\u{20ac}')
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::{error, fmt, io};
+
+use crate::encoding::Encoding;
+
+#[derive(Debug)]
+pub enum FileError {
+    IsDirectory(String),
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Decode {
+        path: String,
+        encoding: Encoding,
+    },
+    Encode {
+        path: String,
+        encoding: Encoding,
+        character: char,
+    },
+}
+
+impl FileError {
+    pub fn io(path: &str, source: io::Error) -> Self {
+        FileError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::IsDirectory(path) => write!(f, "{}: is a directory", path),
+            FileError::Io { path, source } => write!(f, "{}: {}", path, source),
+            FileError::Decode { path, encoding } => write!(f, "{}: not valid {}", path, encoding),
+            FileError::Encode {
+                path,
+                encoding,
+                character,
+            } => write!(
+                f,
+                "{}: {:?} can't be encoded as {}",
+                path, character, encoding
+            ),
+        }
+    }
+}
+
+impl error::Error for FileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FileError::IsDirectory(_) | FileError::Decode { .. } | FileError::Encode { .. } => None,
+            FileError::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Parse {
+        path: String,
+        source: toml::de::Error,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {}", path, source),
+            ConfigError::Parse { path, source } => write!(f, "{}: {}", path, source),
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// How `write_atomic` saved the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Written {
+    Atomic,
+    /// Overwritten in place because a replacement couldn't be set up, so a
+    /// crash mid-write could have truncated it.
+    InPlace,
+}
+
+/// Writes to a temporary file next to `path`, syncs it and renames it over
+/// the target. With `backup` the old version is copied to `path~` first.
+pub fn write_atomic(path: &Path, contents: &[u8], backup: bool) -> io::Result<Written> {
+    // Write through symlinks instead of replacing the link itself.
+    let target = match fs::canonicalize(path) {
+        Ok(real) => real,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(err) => return Err(err),
+    };
+    let original = match fs::metadata(&target) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+
+    if original.is_some() {
+        // Renaming only needs the directory to be writable, so check the file
+        // itself the way writing to it directly would.
+        OpenOptions::new().write(true).open(&target)?;
+    }
+
+    if backup && original.is_some() {
+        fs::copy(&target, backup_path(&target))?;
+    }
+
+    let temp = temp_path(&target);
+    let result = write_temp(&temp, contents, original.as_ref());
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp);
+        return match err.kind() {
+            // No new files allowed in the directory, or not allowed to give
+            // the new file the original owner: overwrite in place, which
+            // keeps ownership intact.
+            io::ErrorKind::PermissionDenied if original.is_some() => {
+                fs::write(&target, contents).map(|()| Written::InPlace)
+            }
+            _ => Err(err),
+        };
+    }
+
+    if let Err(err) = fs::rename(&temp, &target) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+
+    sync_dir(&target);
+    Ok(Written::Atomic)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push("~");
+    PathBuf::from(name)
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, process::id()))
+}
+
+fn write_temp(temp: &Path, contents: &[u8], original: Option<&fs::Metadata>) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // Until it has the original's permissions, only the owner may read the
+    // replacement. New files get the usual default.
+    #[cfg(unix)]
+    if original.is_some() {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(temp)?;
+
+    // Changing the owner can clear the setuid bits, so it goes first, and
+    // both happen before any of the contents land in the file.
+    if let Some(metadata) = original {
+        set_owner(&file, metadata)?;
+        file.set_permissions(metadata.permissions())?;
+    }
+
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_owner(file: &File, metadata: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::{fchown, MetadataExt};
+
+    let current = file.metadata()?;
+    if current.uid() == metadata.uid() && current.gid() == metadata.gid() {
+        return Ok(());
+    }
+    fchown(file, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn set_owner(_file: &File, _metadata: &fs::Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rust-edit-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn creates_and_replaces_files() {
+        let dir = temp_dir("file-write");
+        let path = dir.join("a.txt");
+
+        assert_eq!(write_atomic(&path, b"one", false).unwrap(), Written::Atomic);
+        assert_eq!(write_atomic(&path, b"two", false).unwrap(), Written::Atomic);
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backup_keeps_the_previous_version() {
+        let dir = temp_dir("file-backup");
+        let path = dir.join("a.txt");
+
+        write_atomic(&path, b"one", true).unwrap();
+        assert!(!backup_path(&path).exists());
+
+        write_atomic(&path, b"two", true).unwrap();
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"one");
+        write_atomic(&path, b"three", false).unwrap();
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"one");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("file-mode");
+        let path = dir.join("a.txt");
+        for mode in [0o600, 0o640, 0o755] {
+            fs::write(&path, "old").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+
+            write_atomic(&path, b"new", false).unwrap();
+            assert_eq!(
+                fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+                mode
+            );
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks() {
+        let dir = temp_dir("file-link");
+        let real = dir.join("real.txt");
+        let link = dir.join("link.txt");
+        fs::write(&real, "old").unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        write_atomic(&link, b"new", false).unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&real).unwrap(), b"new");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Permission bits don't stop root, so the checks below only mean
+    /// something for other users.
+    #[cfg(unix)]
+    fn permissions_apply(dir: &Path) -> bool {
+        let probe = dir.join("probe");
+        let denied = fs::write(&probe, "").is_err();
+        let _ = fs::remove_file(probe);
+        denied
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn falls_back_to_writing_in_place() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("file-in-place");
+        let path = dir.join("a.txt");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+
+        if permissions_apply(&dir) {
+            assert_eq!(
+                write_atomic(&path, b"new", false).unwrap(),
+                Written::InPlace
+            );
+            assert_eq!(fs::read(&path).unwrap(), b"new");
+        }
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_read_only_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("file-read-only");
+        let path = dir.join("a.txt");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+
+        if permissions_apply(&dir) {
+            let err = write_atomic(&path, b"new", false).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            assert_eq!(fs::read(&path).unwrap(), b"old");
+        }
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+/// Parses `line`, `line:col`, `+N`/`-N` and `N%` into a zero-based target.
+/// The line is clamped to the document, the column is a display column left
+/// to the caller to map onto the line.
+pub fn parse(input: &str, current_line: usize, line_count: usize) -> Option<Target> {
+    let input = input.trim();
+    let last_line = line_count.saturating_sub(1);
+
+    if let Some(percent) = input.strip_suffix('%') {
+        let percent: f64 = percent.trim().parse().ok()?;
+        if !(0.0..=100.0).contains(&percent) {
+            return None;
+        }
+        return Some(Target {
+            line: (last_line as f64 * percent / 100.0).round() as usize,
+            column: None,
+        });
+    }
+
+    let (line, column) = match input.split_once(':') {
+        Some((line, column)) => (line.trim(), Some(column.trim())),
+        None => (input, None),
+    };
+
+    let line = if let Some(n) = line.strip_prefix('+') {
+        current_line.saturating_add(n.parse().ok()?)
+    } else if let Some(n) = line.strip_prefix('-') {
+        current_line.saturating_sub(n.parse().ok()?)
+    } else if line.is_empty() && column.is_some() {
+        current_line
+    } else {
+        line.parse::<usize>().ok()?.saturating_sub(1)
+    };
+
+    let column = match column {
+        Some(column) => Some(column.parse::<usize>().ok()?.saturating_sub(1)),
+        None => None,
+    };
+
+    Some(Target {
+        line: line.min(last_line),
+        column,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(line: usize) -> Option<Target> {
+        Some(Target { line, column: None })
+    }
+
+    #[test]
+    fn absolute_lines_are_one_based() {
+        assert_eq!(parse("1", 5, 10), line(0));
+        assert_eq!(parse(" 7 ", 5, 10), line(6));
+        assert_eq!(parse("0", 5, 10), line(0));
+    }
+
+    #[test]
+    fn relative_lines() {
+        assert_eq!(parse("+3", 5, 10), line(8));
+        assert_eq!(parse("-3", 5, 10), line(2));
+        assert_eq!(parse("-9", 5, 10), line(0));
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(parse("0%", 5, 11), line(0));
+        assert_eq!(parse("50%", 5, 11), line(5));
+        assert_eq!(parse("100%", 5, 11), line(10));
+        assert_eq!(parse("101%", 5, 11), None);
+    }
+
+    #[test]
+    fn lines_are_clamped_to_the_document() {
+        assert_eq!(parse("99", 5, 10), line(9));
+        assert_eq!(parse("+99", 5, 10), line(9));
+        assert_eq!(parse("1", 0, 0), line(0));
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(
+            parse("3:4", 5, 10),
+            Some(Target {
+                line: 2,
+                column: Some(3)
+            })
+        );
+        assert_eq!(
+            parse(":1", 5, 10),
+            Some(Target {
+                line: 5,
+                column: Some(0)
+            })
+        );
+        assert_eq!(
+            parse("+1:200", 5, 10),
+            Some(Target {
+                line: 6,
+                column: Some(199)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse("", 5, 10), None);
+        assert_eq!(parse("abc", 5, 10), None);
+        assert_eq!(parse("3:x", 5, 10), None);
+        assert_eq!(parse("+", 5, 10), None);
+    }
+}
@@ -1,57 +1,101 @@
 use crate::app::{App, AppResult, Direction};
+use crate::keymap::{Command, Lookup, Motion};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Direction as SplitDirection;
 
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
-    match key_event.modifiers {
-        KeyModifiers::CONTROL | KeyModifiers::ALT => {
-            if key_event.code == KeyCode::Char('c') || key_event.code == KeyCode::Char('C') {
-                app.quit();
+    let in_sequence = !app.keymap.pending().0.is_empty();
+    match app.keymap.feed(key_event.into()) {
+        Lookup::Command(command) => {
+            if in_sequence {
+                app.reset_status();
             }
-            if key_event.code == KeyCode::Char('s') || key_event.code == KeyCode::Char('S') {
-                app.save_to_file();
-            }
-            if key_event.code == KeyCode::Left {
-                app.jump_at_start_line();
-            }
-            if key_event.code == KeyCode::Right {
-                app.jump_at_end_line();
+            if let Err(err) = run(command, app) {
+                app.show_error(err.as_ref());
             }
         }
-        KeyModifiers::SHIFT => {
-            // TODO: implement text selection
+        Lookup::Pending => app.status = format!("{} ...", app.keymap.pending()),
+        Lookup::Unbound(keys) if keys.0.len() > 1 => {
+            app.status = format!("{} is not bound", keys);
         }
-        _ => match key_event.code {
-            KeyCode::Esc => {
-                app.exit_prompt();
-            }
-            KeyCode::Enter => {
-                app.set_dirty();
-                app.add_new_line();
-            }
-            KeyCode::Backspace => {
-                app.set_dirty();
-                app.pop_char();
-            }
-            KeyCode::Left => {
-                app.move_cursor(Direction { x: -1, y: 0 }, false);
-            }
-            KeyCode::Right => {
-                app.move_cursor(Direction { x: 1, y: 0 }, false);
-            }
-            KeyCode::Up => {
-                app.move_cursor(Direction { x: 0, y: 1 }, false);
-            }
-            KeyCode::Down => {
-                app.move_cursor(Direction { x: 0, y: -1 }, false);
-            }
-            _ => {
+        Lookup::Unbound(_) => {
+            // Ctrl + Alt is how AltGr arrives on some platforms.
+            let modifiers = key_event.modifiers - KeyModifiers::SHIFT;
+            if modifiers.is_empty() || modifiers == KeyModifiers::CONTROL | KeyModifiers::ALT {
                 if let KeyCode::Char(c) = key_event.code {
-                    app.set_dirty();
-                    app.insert_char(c)
+                    app.insert_char(c);
                 }
             }
-        },
-    };
+        }
+    }
+
+    Ok(())
+}
 
+fn run(command: Command, app: &mut App) -> AppResult<()> {
+    match command {
+        Command::Quit => app.quit(),
+        Command::Save => app.save_to_file()?,
+        Command::Copy => app.copy()?,
+        Command::Cut => app.cut()?,
+        Command::Paste => app.paste()?,
+        Command::Undo => app.undo(),
+        Command::Redo => app.redo(),
+        Command::Search => app.start_search(),
+        Command::SearchNext => app.search_next(),
+        Command::SearchPrevious => app.search_previous(),
+        Command::Replace => {
+            if app.is_prompt {
+                app.toggle_replace_regex();
+            } else {
+                app.start_replace();
+            }
+        }
+        Command::GotoLine => app.start_goto(),
+        Command::CommandPalette => app.start_command(),
+        Command::Open => app.start_open(),
+        Command::CloseBuffer => app.close_buffer(),
+        Command::NextBuffer => app.cycle_buffer(true),
+        Command::PreviousBuffer => app.cycle_buffer(false),
+        Command::NextPane => app.focus_next_pane(true),
+        Command::PreviousPane => app.focus_next_pane(false),
+        // Like the palette's `split` and `vsplit`: a horizontal split stacks
+        // the panes, a vertical one puts them side by side.
+        Command::SplitHorizontal => app.split_pane(SplitDirection::Vertical),
+        Command::SplitVertical => app.split_pane(SplitDirection::Horizontal),
+        Command::ClosePane => app.close_pane(),
+        Command::GrowPane => app.resize_pane(5),
+        Command::ShrinkPane => app.resize_pane(-5),
+        Command::ToggleWrap => app.toggle_wrap(),
+        Command::ListKeys => app.show_bindings(),
+        Command::Cancel => app.cancel_prompt(),
+        Command::Newline => app.add_new_line(),
+        Command::Backspace => app.pop_char(),
+        Command::Delete => app.delete_char(),
+        Command::DeleteWordBackward => app.delete_word(false),
+        Command::DeleteWordForward => app.delete_word(true),
+        Command::Indent => app.insert_tab(),
+        Command::Outdent => app.outdent(),
+        Command::Move(motion) => move_cursor(app, motion, false),
+        Command::Select(motion) => move_cursor(app, motion, true),
+    }
     Ok(())
 }
+
+fn move_cursor(app: &mut App, motion: Motion, is_selection: bool) {
+    match motion {
+        Motion::Left => app.move_cursor(Direction { x: -1, y: 0 }, is_selection),
+        Motion::Right => app.move_cursor(Direction { x: 1, y: 0 }, is_selection),
+        Motion::Up => app.move_cursor(Direction { x: 0, y: 1 }, is_selection),
+        Motion::Down => app.move_cursor(Direction { x: 0, y: -1 }, is_selection),
+        Motion::Home => app.jump_at_line_home(is_selection),
+        Motion::LineStart => app.jump_at_start_line(is_selection),
+        Motion::End => app.jump_at_end_line(is_selection),
+        Motion::PageUp => app.page_up(is_selection),
+        Motion::PageDown => app.page_down(is_selection),
+        Motion::DocumentStart => app.jump_at_start(is_selection),
+        Motion::DocumentEnd => app.jump_at_end(is_selection),
+        Motion::WordLeft => app.move_word(false, is_selection),
+        Motion::WordRight => app.move_word(true, is_selection),
+    }
+}
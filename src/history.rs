@@ -0,0 +1,275 @@
+use crate::app::Position;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CursorState {
+    pub position: Position,
+    pub offset: Position,
+}
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Insert { at: Position, text: String },
+    Delete { at: Position, text: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    id: usize,
+    typing: bool,
+    pub edits: Vec<Edit>,
+    pub before: CursorState,
+    pub after: CursorState,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    pending: Option<Change>,
+    depth: usize,
+    next_id: usize,
+    saved: Option<usize>,
+    sealed: bool,
+}
+
+/// Position right after `text` when it is inserted at `at`.
+pub fn end_of(at: Position, text: &str) -> Position {
+    match text.rfind('\n') {
+        Some(i) => Position {
+            x: text[i + 1..].chars().count(),
+            y: at.y + text.matches('\n').count(),
+        },
+        None => Position {
+            x: at.x + text.chars().count(),
+            y: at.y,
+        },
+    }
+}
+
+/// Whether typing `more` after `text` starts a new word.
+fn starts_word(text: &str, more: &str) -> bool {
+    text.ends_with(char::is_whitespace) && !more.starts_with(char::is_whitespace)
+}
+
+impl History {
+    pub fn begin(&mut self, before: CursorState) {
+        if self.depth == 0 {
+            self.pending = Some(Change {
+                id: 0,
+                typing: false,
+                edits: Vec::new(),
+                before,
+                after: before,
+            });
+        }
+        self.depth += 1;
+    }
+
+    pub fn push(&mut self, edit: Edit) {
+        if let Some(change) = &mut self.pending {
+            change.edits.push(edit);
+        }
+    }
+
+    pub fn end(&mut self, after: CursorState) {
+        self.finish(after, false);
+    }
+
+    /// Like `end`, but lets the change merge with a directly preceding run
+    /// of typed characters so a whole word, along with the whitespace after
+    /// it, is undone in one step.
+    pub fn end_typing(&mut self, after: CursorState) {
+        self.finish(after, true);
+    }
+
+    fn finish(&mut self, after: CursorState, typing: bool) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth > 0 {
+            return;
+        }
+
+        let Some(mut change) = self.pending.take() else {
+            return;
+        };
+        if change.edits.is_empty() {
+            return;
+        }
+
+        change.after = after;
+        self.redo.clear();
+
+        if typing && self.merge(&change) {
+            return;
+        }
+
+        change.id = self.next_id;
+        change.typing = typing;
+        self.next_id += 1;
+        self.undo.push(change);
+        self.sealed = false;
+    }
+
+    fn merge(&mut self, change: &Change) -> bool {
+        if self.sealed {
+            return false;
+        }
+        let Some(top) = self.undo.last_mut() else {
+            return false;
+        };
+        if !top.typing {
+            return false;
+        }
+
+        match (top.edits.as_mut_slice(), change.edits.as_slice()) {
+            (
+                [Edit::Insert { at, text }],
+                [Edit::Insert {
+                    at: next,
+                    text: more,
+                }],
+            ) if end_of(*at, text) == *next && !more.contains('\n') && !starts_word(text, more) => {
+                text.push_str(more);
+                top.after = change.after;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<Change> {
+        let change = self.undo.pop()?;
+        self.redo.push(change.clone());
+        self.sealed = true;
+        Some(change)
+    }
+
+    pub fn redo(&mut self) -> Option<Change> {
+        let change = self.redo.pop()?;
+        self.undo.push(change.clone());
+        self.sealed = true;
+        Some(change)
+    }
+
+    /// Makes the buffer count as modified until the next save, for changes
+    /// the history doesn't record.
+    pub fn mark_unsaved(&mut self) {
+        self.saved = Some(usize::MAX);
+        self.sealed = true;
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = self.undo.last().map(|c| c.id);
+        self.sealed = true;
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.undo.last().map(|c| c.id) != self.saved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(x: usize) -> CursorState {
+        CursorState {
+            position: Position { x, y: 0 },
+            offset: Position { x: 0, y: 0 },
+        }
+    }
+
+    /// Records typing `text` at column `x` as one keystroke.
+    fn type_at(history: &mut History, x: usize, text: &str) {
+        history.begin(cursor(x));
+        history.push(Edit::Insert {
+            at: Position { x, y: 0 },
+            text: text.into(),
+        });
+        history.end_typing(cursor(x + text.chars().count()));
+    }
+
+    fn type_text(history: &mut History, x: usize, text: &str) {
+        for (i, c) in text.chars().enumerate() {
+            type_at(history, x + i, &c.to_string());
+        }
+    }
+
+    fn inserted(change: &Change) -> &str {
+        match change.edits.as_slice() {
+            [Edit::Insert { text, .. }] => text,
+            edits => panic!("expected a single insert, got {:?}", edits),
+        }
+    }
+
+    #[test]
+    fn typing_is_undone_a_word_at_a_time() {
+        let mut history = History::default();
+        type_text(&mut history, 0, "hello world");
+
+        assert_eq!(inserted(&history.undo().unwrap()), "world");
+        assert_eq!(inserted(&history.undo().unwrap()), "hello ");
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn typing_elsewhere_starts_a_new_change() {
+        let mut history = History::default();
+        type_text(&mut history, 0, "ab");
+        type_at(&mut history, 0, "c");
+
+        assert_eq!(inserted(&history.undo().unwrap()), "c");
+        assert_eq!(inserted(&history.undo().unwrap()), "ab");
+    }
+
+    #[test]
+    fn undo_seals_the_group() {
+        let mut history = History::default();
+        type_text(&mut history, 0, "ab");
+        let undone = history.undo().unwrap();
+        history.redo();
+        type_at(&mut history, 2, "c");
+
+        assert_eq!(inserted(&undone), "ab");
+        assert_eq!(inserted(&history.undo().unwrap()), "c");
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = History::default();
+        type_text(&mut history, 0, "ab");
+        history.undo();
+        type_at(&mut history, 0, "x");
+
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn modified_relative_to_the_last_save() {
+        let mut history = History::default();
+        assert!(!history.is_modified());
+
+        type_text(&mut history, 0, "ab");
+        assert!(history.is_modified());
+        history.mark_saved();
+        assert!(!history.is_modified());
+
+        // Typing after a save doesn't merge into the saved change.
+        type_at(&mut history, 2, "c");
+        assert!(history.is_modified());
+        history.undo();
+        assert!(!history.is_modified());
+        history.undo();
+        assert!(history.is_modified());
+        history.redo();
+        assert!(!history.is_modified());
+    }
+
+    #[test]
+    fn unrecorded_changes_stay_modified_until_saved() {
+        let mut history = History::default();
+        history.mark_unsaved();
+        assert!(history.is_modified());
+        history.mark_saved();
+        assert!(!history.is_modified());
+    }
+}
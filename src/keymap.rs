@@ -0,0 +1,676 @@
+use std::{fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    LineStart,
+    End,
+    PageUp,
+    PageDown,
+    DocumentStart,
+    DocumentEnd,
+    WordLeft,
+    WordRight,
+}
+
+const MOTIONS: &[(&str, Motion)] = &[
+    ("left", Motion::Left),
+    ("right", Motion::Right),
+    ("up", Motion::Up),
+    ("down", Motion::Down),
+    ("home", Motion::Home),
+    ("line-start", Motion::LineStart),
+    ("end", Motion::End),
+    ("page-up", Motion::PageUp),
+    ("page-down", Motion::PageDown),
+    ("document-start", Motion::DocumentStart),
+    ("document-end", Motion::DocumentEnd),
+    ("word-left", Motion::WordLeft),
+    ("word-right", Motion::WordRight),
+];
+
+/// Editor actions that can be bound to keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Save,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Replace,
+    GotoLine,
+    CommandPalette,
+    Open,
+    CloseBuffer,
+    NextBuffer,
+    PreviousBuffer,
+    NextPane,
+    PreviousPane,
+    SplitHorizontal,
+    SplitVertical,
+    ClosePane,
+    GrowPane,
+    ShrinkPane,
+    ToggleWrap,
+    ListKeys,
+    Cancel,
+    Newline,
+    Backspace,
+    Delete,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Indent,
+    Outdent,
+    Move(Motion),
+    Select(Motion),
+}
+
+const COMMANDS: &[(&str, Command)] = &[
+    ("quit", Command::Quit),
+    ("save", Command::Save),
+    ("copy", Command::Copy),
+    ("cut", Command::Cut),
+    ("paste", Command::Paste),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("search", Command::Search),
+    ("search-next", Command::SearchNext),
+    ("search-previous", Command::SearchPrevious),
+    ("replace", Command::Replace),
+    ("goto-line", Command::GotoLine),
+    ("command-palette", Command::CommandPalette),
+    ("open", Command::Open),
+    ("close-buffer", Command::CloseBuffer),
+    ("next-buffer", Command::NextBuffer),
+    ("previous-buffer", Command::PreviousBuffer),
+    ("next-pane", Command::NextPane),
+    ("previous-pane", Command::PreviousPane),
+    ("split-horizontal", Command::SplitHorizontal),
+    ("split-vertical", Command::SplitVertical),
+    ("close-pane", Command::ClosePane),
+    ("grow-pane", Command::GrowPane),
+    ("shrink-pane", Command::ShrinkPane),
+    ("toggle-wrap", Command::ToggleWrap),
+    ("list-keys", Command::ListKeys),
+    ("cancel", Command::Cancel),
+    ("newline", Command::Newline),
+    ("backspace", Command::Backspace),
+    ("delete", Command::Delete),
+    ("delete-word-backward", Command::DeleteWordBackward),
+    ("delete-word-forward", Command::DeleteWordForward),
+    ("indent", Command::Indent),
+    ("outdent", Command::Outdent),
+];
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Motions are bound by name, and with a `select-` prefix to extend the
+    /// selection.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let (motion, select) = match name.strip_prefix("select-") {
+            Some(motion) => (motion, true),
+            None => (name, false),
+        };
+        if let Some(&(_, motion)) = MOTIONS.iter().find(|(n, _)| *n == motion) {
+            return Ok(if select {
+                Command::Select(motion)
+            } else {
+                Command::Move(motion)
+            });
+        }
+        COMMANDS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, command)| command)
+            .ok_or_else(|| format!("unknown command `{}`", name))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let motion_name = |motion: &Motion| {
+            MOTIONS
+                .iter()
+                .find(|(_, m)| m == motion)
+                .map_or("", |(name, _)| name)
+        };
+        match self {
+            Command::Move(motion) => write!(f, "{}", motion_name(motion)),
+            Command::Select(motion) => write!(f, "select-{}", motion_name(motion)),
+            command => {
+                let name = COMMANDS
+                    .iter()
+                    .find(|(_, c)| c == command)
+                    .map_or("", |(name, _)| name);
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Space", KeyCode::Char(' ')),
+];
+
+/// A key together with the modifiers held down, as written in bindings:
+/// `Ctrl+Shift+Z`, `Alt+=`, `F6`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Terminals disagree on how Shift is reported: letters come upper-case
+    /// with or without the flag, symbols already carry it and Shift+Tab is
+    /// a key of its own. Letters are stored lower-case with Shift, other
+    /// characters without it.
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let (code, modifiers) = match code {
+            KeyCode::Char(c) if c.is_uppercase() => (
+                KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+                modifiers | KeyModifiers::SHIFT,
+            ),
+            KeyCode::Char(c) if !c.is_alphabetic() && c != ' ' => {
+                (code, modifiers - KeyModifiers::SHIFT)
+            }
+            KeyCode::BackTab => (KeyCode::Tab, modifiers | KeyModifiers::SHIFT),
+            _ => (code, modifiers),
+        };
+        Self {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::normalized(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (modifiers, name) = if text == "+" {
+            ("", "+")
+        } else if let Some(modifiers) = text.strip_suffix("++") {
+            (modifiers, "+")
+        } else {
+            text.rsplit_once('+').unwrap_or(("", text))
+        };
+
+        let mut flags = KeyModifiers::NONE;
+        if !modifiers.is_empty() {
+            for modifier in modifiers.split('+') {
+                flags |= match modifier.to_ascii_lowercase().as_str() {
+                    "ctrl" => KeyModifiers::CONTROL,
+                    "alt" => KeyModifiers::ALT,
+                    "shift" => KeyModifiers::SHIFT,
+                    _ => return Err(format!("unknown modifier `{}` in `{}`", modifier, text)),
+                };
+            }
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+            _ => KEY_NAMES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|&(_, code)| code)
+                .or_else(|| {
+                    let number = name.strip_prefix(['F', 'f'])?.parse().ok()?;
+                    (1..=24).contains(&number).then_some(KeyCode::F(number))
+                })
+                .ok_or_else(|| format!("unknown key `{}` in `{}`", name, text))?,
+        };
+        Ok(Key::normalized(code, flags))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(flag) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::F(number) => write!(f, "F{}", number),
+            code => match KEY_NAMES.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => match code {
+                    KeyCode::Char(c) => write!(f, "{}", c.to_uppercase()),
+                    code => write!(f, "{:?}", code),
+                },
+            },
+        }
+    }
+}
+
+/// Keys pressed one after another, written separated by spaces:
+/// `Ctrl+K Ctrl+C`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence(pub Vec<Key>);
+
+impl FromStr for Sequence {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let keys = text
+            .split_whitespace()
+            .map(Key::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("empty key binding".into());
+        }
+        Ok(Sequence(keys))
+    }
+}
+
+impl<'de> Deserialize<'de> for Sequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bindings from the `[keys]` table of the config, mapping a sequence to a
+/// command name, or to `"none"` to remove a default binding.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings(pub Vec<(Sequence, Option<Command>)>);
+
+struct Target(Option<Command>);
+
+impl<'de> Deserialize<'de> for Target {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "none" => Ok(Target(None)),
+            name => name
+                .parse()
+                .map(Some)
+                .map(Target)
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BindingsVisitor;
+
+        impl<'de> Visitor<'de> for BindingsVisitor {
+            type Value = Bindings;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a table of key bindings")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Bindings, A::Error> {
+                let mut bindings = Vec::new();
+                while let Some((keys, Target(command))) = map.next_entry()? {
+                    bindings.push((keys, command));
+                }
+                Ok(Bindings(bindings))
+            }
+        }
+
+        deserializer.deserialize_map(BindingsVisitor)
+    }
+}
+
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("Ctrl+Q", "quit"),
+    ("Ctrl+S", "save"),
+    ("Ctrl+C", "copy"),
+    ("Ctrl+X", "cut"),
+    ("Ctrl+V", "paste"),
+    ("Ctrl+Z", "undo"),
+    ("Ctrl+Y", "redo"),
+    ("Ctrl+Shift+Z", "redo"),
+    ("Ctrl+F", "search"),
+    ("F3", "search-next"),
+    ("Shift+F3", "search-previous"),
+    ("Ctrl+R", "replace"),
+    ("Ctrl+G", "goto-line"),
+    ("Ctrl+P", "command-palette"),
+    ("Ctrl+O", "open"),
+    ("Ctrl+W", "close-buffer"),
+    ("Ctrl+PageDown", "next-buffer"),
+    ("Ctrl+PageUp", "previous-buffer"),
+    ("F6", "next-pane"),
+    ("Shift+F6", "previous-pane"),
+    ("Alt+S", "split-horizontal"),
+    ("Alt+V", "split-vertical"),
+    ("Alt+X", "close-pane"),
+    ("Alt+=", "grow-pane"),
+    ("Alt++", "grow-pane"),
+    ("Alt+-", "shrink-pane"),
+    ("Alt+Z", "toggle-wrap"),
+    ("F1", "list-keys"),
+    ("Esc", "cancel"),
+    ("Enter", "newline"),
+    ("Shift+Enter", "newline"),
+    ("Backspace", "backspace"),
+    ("Shift+Backspace", "backspace"),
+    ("Delete", "delete"),
+    ("Ctrl+Backspace", "delete-word-backward"),
+    ("Alt+Backspace", "delete-word-backward"),
+    ("Ctrl+H", "delete-word-backward"),
+    ("Ctrl+Delete", "delete-word-forward"),
+    ("Alt+Delete", "delete-word-forward"),
+    ("Tab", "indent"),
+    ("Shift+Tab", "outdent"),
+    ("Left", "left"),
+    ("Right", "right"),
+    ("Up", "up"),
+    ("Down", "down"),
+    ("Home", "home"),
+    ("End", "end"),
+    ("PageUp", "page-up"),
+    ("PageDown", "page-down"),
+    ("Alt+Left", "line-start"),
+    ("Alt+Right", "end"),
+    ("Ctrl+Home", "document-start"),
+    ("Ctrl+End", "document-end"),
+    ("Ctrl+Left", "word-left"),
+    ("Ctrl+Right", "word-right"),
+    ("Shift+Left", "select-left"),
+    ("Shift+Right", "select-right"),
+    ("Shift+Up", "select-up"),
+    ("Shift+Down", "select-down"),
+    ("Shift+Home", "select-home"),
+    ("Shift+End", "select-end"),
+    ("Shift+PageUp", "select-page-up"),
+    ("Shift+PageDown", "select-page-down"),
+    ("Ctrl+Shift+Home", "select-document-start"),
+    ("Ctrl+Shift+End", "select-document-end"),
+    ("Ctrl+Shift+Left", "select-word-left"),
+    ("Ctrl+Shift+Right", "select-word-right"),
+];
+
+pub enum Lookup {
+    Command(Command),
+    /// The keys so far start a longer sequence.
+    Pending,
+    Unbound(Sequence),
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Sequence, Command)>,
+    pending: Vec<Key>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+        };
+        for (keys, command) in DEFAULT_BINDINGS {
+            keymap.bind(
+                keys.parse().expect("invalid default key"),
+                Some(command.parse().expect("invalid default command")),
+            );
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Binds `keys` to `command`, or unbinds them when `None`. A sequence
+    /// can't also start a longer one, so bindings that overlap it go.
+    pub fn bind(&mut self, keys: Sequence, command: Option<Command>) {
+        self.bindings
+            .retain(|(bound, _)| !bound.0.starts_with(&keys.0) && !keys.0.starts_with(&bound.0));
+        if let Some(command) = command {
+            self.bindings.push((keys, command));
+        }
+    }
+
+    pub fn feed(&mut self, key: Key) -> Lookup {
+        self.pending.push(key);
+        if let Some(&(_, command)) = self
+            .bindings
+            .iter()
+            .find(|(keys, _)| keys.0 == self.pending)
+        {
+            self.pending.clear();
+            return Lookup::Command(command);
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|(keys, _)| keys.0.starts_with(&self.pending))
+        {
+            return Lookup::Pending;
+        }
+        Lookup::Unbound(Sequence(std::mem::take(&mut self.pending)))
+    }
+
+    pub fn pending(&self) -> Sequence {
+        Sequence(self.pending.clone())
+    }
+
+    pub fn bindings(&self) -> &[(Sequence, Command)] {
+        &self.bindings
+    }
+
+    /// The first sequence bound to `command`, if any.
+    pub fn keys_for(&self, command: Command) -> Option<&Sequence> {
+        self.bindings
+            .iter()
+            .find(|&&(_, bound)| bound == command)
+            .map(|(keys, _)| keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::from(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn parses_plus_as_a_key() {
+        let plus = key(KeyCode::Char('+'), KeyModifiers::NONE);
+        assert_eq!("+".parse(), Ok(plus));
+        assert_eq!(
+            "Alt++".parse(),
+            Ok(key(KeyCode::Char('+'), KeyModifiers::ALT))
+        );
+        assert_eq!(
+            "Ctrl+Alt++".parse(),
+            Ok(key(
+                KeyCode::Char('+'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            ))
+        );
+    }
+
+    #[test]
+    fn shift_tab_matches_back_tab() {
+        let shift_tab: Key = "Shift+Tab".parse().unwrap();
+        assert_eq!(shift_tab, key(KeyCode::Tab, KeyModifiers::SHIFT));
+        assert_eq!(event(KeyCode::BackTab, KeyModifiers::NONE), shift_tab);
+        assert_eq!(event(KeyCode::BackTab, KeyModifiers::SHIFT), shift_tab);
+        assert_eq!(shift_tab.to_string(), "Shift+Tab");
+    }
+
+    #[test]
+    fn uppercase_letters_imply_shift() {
+        let redo = key(
+            KeyCode::Char('z'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert_eq!("Ctrl+Shift+Z".parse(), Ok(redo));
+        assert_eq!("ctrl+shift+z".parse(), Ok(redo));
+        assert_eq!(event(KeyCode::Char('Z'), KeyModifiers::CONTROL), redo);
+        assert_eq!(
+            event(
+                KeyCode::Char('Z'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT
+            ),
+            redo
+        );
+        assert_eq!(
+            "Ctrl+Z".parse(),
+            Ok(key(KeyCode::Char('z'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(redo.to_string(), "Ctrl+Shift+Z");
+    }
+
+    #[test]
+    fn symbols_drop_shift() {
+        assert_eq!(
+            event(KeyCode::Char('+'), KeyModifiers::ALT | KeyModifiers::SHIFT),
+            "Alt++".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(
+            "Hyper+A".parse::<Key>(),
+            Err("unknown modifier `Hyper` in `Hyper+A`".into())
+        );
+        assert_eq!(
+            "Ctrl+Foo".parse::<Key>(),
+            Err("unknown key `Foo` in `Ctrl+Foo`".into())
+        );
+        assert_eq!(
+            "F25".parse::<Key>().map(|_| ()),
+            Err("unknown key `F25` in `F25`".into())
+        );
+        assert!("  ".parse::<Sequence>().is_err());
+    }
+
+    #[test]
+    fn parses_sequences() {
+        let sequence: Sequence = "Ctrl+K  Ctrl+C".parse().unwrap();
+        assert_eq!(
+            sequence.0,
+            [
+                key(KeyCode::Char('k'), KeyModifiers::CONTROL),
+                key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            ]
+        );
+        assert_eq!(sequence.to_string(), "Ctrl+K Ctrl+C");
+    }
+
+    #[test]
+    fn feed_waits_for_the_rest_of_a_sequence() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Ctrl+K Ctrl+C".parse().unwrap(), Some(Command::Copy));
+
+        let ctrl_k = event(KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert!(matches!(keymap.feed(ctrl_k), Lookup::Pending));
+        assert_eq!(keymap.pending().to_string(), "Ctrl+K");
+        assert!(matches!(
+            keymap.feed(event(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Lookup::Command(Command::Copy)
+        ));
+        assert!(keymap.pending().0.is_empty());
+    }
+
+    #[test]
+    fn feed_reports_unbound_sequences() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Ctrl+K Ctrl+C".parse().unwrap(), Some(Command::Copy));
+
+        keymap.feed(event(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        match keymap.feed(event(KeyCode::Char('x'), KeyModifiers::NONE)) {
+            Lookup::Unbound(keys) => assert_eq!(keys.to_string(), "Ctrl+K X"),
+            _ => panic!("expected an unbound sequence"),
+        }
+        assert!(keymap.pending().0.is_empty());
+
+        match keymap.feed(event(KeyCode::Char('a'), KeyModifiers::NONE)) {
+            Lookup::Unbound(keys) => assert_eq!(keys.to_string(), "A"),
+            _ => panic!("expected an unbound key"),
+        }
+    }
+
+    #[test]
+    fn binding_a_prefix_replaces_longer_sequences() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Ctrl+K Ctrl+C".parse().unwrap(), Some(Command::Copy));
+        keymap.bind("Ctrl+K".parse().unwrap(), Some(Command::Cut));
+
+        assert!(matches!(
+            keymap.feed(event(KeyCode::Char('k'), KeyModifiers::CONTROL)),
+            Lookup::Command(Command::Cut)
+        ));
+        assert!(keymap
+            .keys_for(Command::Copy)
+            .is_some_and(|keys| keys.to_string() == "Ctrl+C"));
+    }
+
+    #[test]
+    fn pane_commands_are_bound_by_default() {
+        let keymap = Keymap::default();
+        for name in ["split-horizontal", "split-vertical", "close-pane"] {
+            let command: Command = name.parse().unwrap();
+            assert_eq!(command.to_string(), name);
+            assert!(keymap.keys_for(command).is_some(), "{} is unbound", name);
+        }
+    }
+}
@@ -8,4 +8,34 @@ pub mod tui;
 
 pub mod handler;
 
-pub mod gap_buffer;
+pub mod document;
+
+pub mod error;
+
+pub mod file;
+
+pub mod history;
+
+pub mod clipboard;
+
+pub mod search;
+
+pub mod goto;
+
+pub mod word;
+
+pub mod text;
+
+pub mod line_ending;
+
+pub mod encoding;
+
+pub mod buffer;
+
+pub mod pane;
+
+pub mod config;
+
+pub mod theme;
+
+pub mod keymap;
@@ -0,0 +1,168 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lf" | "unix" => Some(LineEnding::Lf),
+            "crlf" | "dos" | "windows" => Some(LineEnding::Crlf),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::Crlf => write!(f, "CRLF"),
+        }
+    }
+}
+
+/// How the lines of a file were terminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub line_ending: LineEnding,
+    pub mixed: bool,
+    pub trailing_newline: bool,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::Lf,
+            mixed: false,
+            trailing_newline: true,
+        }
+    }
+}
+
+/// Splits `contents` into lines without their terminators. With mixed
+/// endings the more common one is reported.
+pub fn split(contents: &str) -> (Vec<&str>, Format) {
+    let mut lines: Vec<&str> = contents.split('\n').collect();
+    let last = lines.pop().unwrap_or_default();
+
+    let mut crlf = 0;
+    for line in &mut lines {
+        if let Some(stripped) = line.strip_suffix('\r') {
+            *line = stripped;
+            crlf += 1;
+        }
+    }
+    let lf = lines.len() - crlf;
+
+    let trailing_newline = !lines.is_empty() && last.is_empty();
+    if !trailing_newline {
+        lines.push(last);
+    }
+
+    let format = Format {
+        line_ending: if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        },
+        mixed: crlf > 0 && lf > 0,
+        trailing_newline,
+    };
+    (lines, format)
+}
+
+pub fn join<I, S>(lines: I, format: Format) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let ending = format.line_ending.as_str();
+    let mut contents = String::new();
+    for (i, line) in lines.into_iter().enumerate() {
+        if i > 0 {
+            contents.push_str(ending);
+        }
+        contents.push_str(line.as_ref());
+    }
+    if format.trailing_newline {
+        contents.push_str(ending);
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(contents: &str) -> (Vec<&str>, Format) {
+        let (lines, format) = split(contents);
+        assert_eq!(join(&lines, format), contents);
+        (lines, format)
+    }
+
+    #[test]
+    fn lf() {
+        let (lines, format) = round_trip("a\nb\n");
+        assert_eq!(lines, ["a", "b"]);
+        assert_eq!(format, Format::default());
+    }
+
+    #[test]
+    fn crlf() {
+        let (lines, format) = round_trip("a\r\nb\r\n");
+        assert_eq!(lines, ["a", "b"]);
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert!(!format.mixed);
+        assert!(format.trailing_newline);
+    }
+
+    #[test]
+    fn mixed_reports_the_more_common_ending() {
+        let (lines, format) = split("a\r\nb\nc\r\n");
+        assert_eq!(lines, ["a", "b", "c"]);
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert!(format.mixed);
+        assert_eq!(join(&lines, format), "a\r\nb\r\nc\r\n");
+    }
+
+    #[test]
+    fn no_trailing_newline() {
+        let (lines, format) = round_trip("a\r\nb");
+        assert_eq!(lines, ["a", "b"]);
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert!(!format.trailing_newline);
+    }
+
+    #[test]
+    fn empty_file() {
+        let (lines, format) = round_trip("");
+        assert_eq!(lines, [""]);
+        assert!(!format.trailing_newline);
+    }
+
+    #[test]
+    fn lone_newline() {
+        let (lines, format) = round_trip("\n");
+        assert_eq!(lines, [""]);
+        assert!(format.trailing_newline);
+    }
+
+    #[test]
+    fn stray_carriage_returns_stay_in_the_line() {
+        let (lines, format) = round_trip("a\rb\nc\r");
+        assert_eq!(lines, ["a\rb", "c\r"]);
+        assert_eq!(format.line_ending, LineEnding::Lf);
+        assert!(!format.mixed);
+    }
+}
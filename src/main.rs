@@ -1,17 +1,54 @@
+use std::path::PathBuf;
+
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 use rust_edit::app::{App, AppResult};
+use rust_edit::clipboard;
+use rust_edit::config;
 use rust_edit::event::{Event, EventHandler};
 use rust_edit::handler::handle_key_events;
 use rust_edit::tui::Tui;
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    let mut config_path = None;
+    let mut paths = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            let Some(path) = args.next() else {
+                eprintln!("rust-edit: --config needs a path");
+                std::process::exit(1);
+            };
+            config_path = Some(PathBuf::from(path));
+        } else {
+            paths.push(arg);
+        }
+    }
+
+    let config = match config::load(config_path.as_deref(), &std::env::current_dir()?) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("rust-edit: {}", err);
+            std::process::exit(1);
+        }
+    };
+
     let mut app = App::new();
+    app.configure(&config);
+    app.clipboard = clipboard::detect(config.clipboard.as_ref());
+
+    for path in paths {
+        if let Err(err) = app.open_buffer(&path) {
+            eprintln!("rust-edit: cannot open {}", err);
+            std::process::exit(1);
+        }
+    }
+    app.switch_buffer(0);
 
     let backend = CrosstermBackend::new(std::io::stderr());
     let terminal = Terminal::new(backend)?;
-    let events = EventHandler::new(250);
+    let events = EventHandler::new(config.tick_rate);
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
     app.window_size = tui.get_terminal_size()?;
@@ -25,6 +62,10 @@ async fn main() -> AppResult<()> {
                 tui.draw(&mut app)?;
             }
             Event::Mouse(_) => tui.draw(&mut app)?,
+            Event::Paste(text) => {
+                app.insert_text(&text);
+                tui.draw(&mut app)?;
+            }
             Event::Resize(_, _) => {
                 app.window_size = tui.get_terminal_size()?;
                 tui.draw(&mut app)?;
@@ -0,0 +1,256 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::app::Position;
+
+/// A view onto a buffer. The focused pane keeps its cursor in the buffer
+/// itself; the others remember theirs here.
+#[derive(Debug, Clone, Copy)]
+pub struct Pane {
+    pub buffer: usize,
+    pub cursor_position: Position,
+    pub cursor_offset: Position,
+}
+
+/// How the panes share the screen: a tree of splits with panes as leaves.
+#[derive(Debug)]
+pub enum Node {
+    Pane(usize),
+    Split {
+        direction: Direction,
+        percent: u16,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+/// Screen areas of every pane and of the lines drawn between them.
+#[derive(Debug, Default)]
+pub struct PaneLayout {
+    pub panes: Vec<(usize, Rect)>,
+    pub separators: Vec<Rect>,
+}
+
+impl Node {
+    pub fn layout(&self, area: Rect) -> PaneLayout {
+        let mut layout = PaneLayout::default();
+        self.collect(area, &mut layout);
+        layout
+    }
+
+    fn collect(&self, area: Rect, layout: &mut PaneLayout) {
+        match self {
+            Node::Pane(pane) => layout.panes.push((*pane, area)),
+            Node::Split {
+                direction,
+                percent,
+                first,
+                second,
+            } => {
+                let areas = Layout::default()
+                    .direction(*direction)
+                    .constraints([
+                        Constraint::Percentage(*percent),
+                        Constraint::Length(1),
+                        Constraint::Min(0),
+                    ])
+                    .split(area);
+                first.collect(areas[0], layout);
+                layout.separators.push(areas[1]);
+                second.collect(areas[2], layout);
+            }
+        }
+    }
+
+    /// Panes in screen order.
+    pub fn panes(&self) -> Vec<usize> {
+        self.layout(Rect::default())
+            .panes
+            .into_iter()
+            .map(|(pane, _)| pane)
+            .collect()
+    }
+
+    /// Splits `pane` in two, putting `new` after it.
+    pub fn split(&mut self, pane: usize, new: usize, direction: Direction) -> bool {
+        match self {
+            Node::Pane(p) if *p == pane => {
+                *self = Node::Split {
+                    direction,
+                    percent: 50,
+                    first: Box::new(Node::Pane(pane)),
+                    second: Box::new(Node::Pane(new)),
+                };
+                true
+            }
+            Node::Pane(_) => false,
+            Node::Split { first, second, .. } => {
+                first.split(pane, new, direction) || second.split(pane, new, direction)
+            }
+        }
+    }
+
+    /// Removes `pane`, letting its sibling take its space, and renumbers the
+    /// panes after it.
+    pub fn remove(&mut self, pane: usize) {
+        self.remove_leaf(pane);
+        self.renumber(pane);
+    }
+
+    fn remove_leaf(&mut self, pane: usize) -> bool {
+        let Node::Split { first, second, .. } = self else {
+            return false;
+        };
+        let sibling = match (first.as_ref(), second.as_ref()) {
+            (Node::Pane(p), _) if *p == pane => std::mem::replace(second.as_mut(), Node::Pane(0)),
+            (_, Node::Pane(p)) if *p == pane => std::mem::replace(first.as_mut(), Node::Pane(0)),
+            _ => return first.remove_leaf(pane) || second.remove_leaf(pane),
+        };
+        *self = sibling;
+        true
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Node::Pane(p) if *p > removed => *p -= 1,
+            Node::Pane(_) => {}
+            Node::Split { first, second, .. } => {
+                first.renumber(removed);
+                second.renumber(removed);
+            }
+        }
+    }
+
+    /// Moves the edge of the innermost split holding `pane` so the pane
+    /// grows by `delta` percent, or shrinks when negative.
+    pub fn resize(&mut self, pane: usize, delta: i16) -> bool {
+        let Node::Split {
+            percent,
+            first,
+            second,
+            ..
+        } = self
+        else {
+            return false;
+        };
+        if first.resize(pane, delta) || second.resize(pane, delta) {
+            return true;
+        }
+
+        let delta = if first.contains(pane) {
+            delta
+        } else if second.contains(pane) {
+            -delta
+        } else {
+            return false;
+        };
+        *percent = (*percent as i16 + delta).clamp(10, 90) as u16;
+        true
+    }
+
+    fn contains(&self, pane: usize) -> bool {
+        match self {
+            Node::Pane(p) => *p == pane,
+            Node::Split { first, second, .. } => first.contains(pane) || second.contains(pane),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 20,
+    };
+
+    /// Splits of the tree, outermost first.
+    fn percents(node: &Node) -> Vec<u16> {
+        match node {
+            Node::Pane(_) => Vec::new(),
+            Node::Split {
+                percent,
+                first,
+                second,
+                ..
+            } => [vec![*percent], percents(first), percents(second)].concat(),
+        }
+    }
+
+    #[test]
+    fn split_puts_the_new_pane_after() {
+        let mut root = Node::Pane(0);
+        assert!(root.split(0, 1, Direction::Horizontal));
+        assert!(root.split(0, 2, Direction::Vertical));
+        assert!(!root.split(7, 3, Direction::Vertical));
+        assert_eq!(root.panes(), [0, 2, 1]);
+    }
+
+    #[test]
+    fn layout_shares_the_area() {
+        let mut root = Node::Pane(0);
+        root.split(0, 1, Direction::Horizontal);
+        root.split(1, 2, Direction::Vertical);
+
+        let layout = root.layout(AREA);
+        assert_eq!(layout.panes[0], (0, Rect::new(0, 0, 40, 20)));
+        assert_eq!(layout.separators[0], Rect::new(40, 0, 1, 20));
+        assert_eq!(layout.panes[1], (1, Rect::new(41, 0, 39, 10)));
+        assert_eq!(layout.separators[1], Rect::new(41, 10, 39, 1));
+        assert_eq!(layout.panes[2], (2, Rect::new(41, 11, 39, 9)));
+    }
+
+    #[test]
+    fn remove_gives_the_space_to_the_sibling() {
+        let mut root = Node::Pane(0);
+        root.split(0, 1, Direction::Horizontal);
+        root.split(1, 2, Direction::Vertical);
+
+        root.remove(1);
+        assert_eq!(root.panes(), [0, 1]);
+        let layout = root.layout(AREA);
+        assert_eq!(layout.panes[1], (1, Rect::new(41, 0, 39, 20)));
+        assert_eq!(layout.separators.len(), 1);
+
+        root.remove(0);
+        assert_eq!(root.panes(), [0]);
+        assert_eq!(root.layout(AREA).panes, [(0, AREA)]);
+        assert!(root.layout(AREA).separators.is_empty());
+    }
+
+    #[test]
+    fn remove_renumbers_later_panes() {
+        let mut root = Node::Pane(0);
+        root.split(0, 1, Direction::Horizontal);
+        root.split(0, 2, Direction::Horizontal);
+
+        root.remove(0);
+        assert_eq!(root.panes(), [1, 0]);
+    }
+
+    #[test]
+    fn resize_moves_the_innermost_edge() {
+        let mut root = Node::Pane(0);
+        assert!(!root.resize(0, 5));
+
+        root.split(0, 1, Direction::Horizontal);
+        root.split(1, 2, Direction::Vertical);
+        assert!(root.resize(0, 5));
+        assert_eq!(percents(&root), [55, 50]);
+        assert!(root.resize(2, 5));
+        assert_eq!(percents(&root), [55, 45]);
+        assert!(!root.resize(9, 5));
+    }
+
+    #[test]
+    fn resize_is_clamped() {
+        let mut root = Node::Pane(0);
+        root.split(0, 1, Direction::Horizontal);
+        root.resize(0, 100);
+        assert_eq!(percents(&root), [90]);
+        root.resize(0, -100);
+        assert_eq!(percents(&root), [10]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+/// Character columns of every non-overlapping occurrence of `query` in `line`.
+pub fn find_in_line(line: &str, query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut columns = Vec::new();
+    let mut last_byte = 0;
+    let mut last_column = 0;
+
+    for (byte, _) in line.match_indices(query) {
+        last_column += line[last_byte..byte].chars().count();
+        last_byte = byte;
+        columns.push(last_column);
+    }
+    columns
+}
+
+pub fn compile(pattern: &str, is_regex: bool) -> Result<Regex, regex::Error> {
+    if is_regex {
+        Regex::new(pattern)
+    } else {
+        Regex::new(&regex::escape(pattern))
+    }
+}
+
+#[derive(Debug)]
+pub struct Replacer {
+    regex: Regex,
+    replacement: String,
+    expand: bool,
+}
+
+impl Replacer {
+    /// In regex mode `$1`/`${name}` refer to capture groups and `\n`/`\t`
+    /// in the replacement stand for a line break and a tab.
+    pub fn new(pattern: &str, replacement: &str, is_regex: bool) -> Result<Self, regex::Error> {
+        let replacement = if is_regex {
+            replacement.replace("\\n", "\n").replace("\\t", "\t")
+        } else {
+            replacement.into()
+        };
+
+        Ok(Self {
+            regex: compile(pattern, is_regex)?,
+            replacement,
+            expand: is_regex,
+        })
+    }
+
+    /// Next match starting at or after `start` that ends before `limit`,
+    /// together with its expanded replacement. The whole text is searched so
+    /// that anchors and word boundaries see what follows `limit`.
+    pub fn find_at(
+        &self,
+        text: &str,
+        start: usize,
+        limit: usize,
+    ) -> Option<(Range<usize>, String)> {
+        let captures = self.regex.captures_at(text, start)?;
+        let range = captures.get(0)?.range();
+        if range.end > limit {
+            return None;
+        }
+
+        let replacement = if self.expand {
+            let mut expanded = String::new();
+            captures.expand(&self.replacement, &mut expanded);
+            expanded
+        } else {
+            self.replacement.clone()
+        };
+
+        Some((range, replacement))
+    }
+
+    pub fn find_all(&self, text: &str, start: usize, limit: usize) -> Vec<(Range<usize>, String)> {
+        let mut matches = Vec::new();
+        let mut next = start;
+
+        while next <= limit {
+            let Some((range, replacement)) = self.find_at(text, next, limit) else {
+                break;
+            };
+            next = skip_match(text, &range);
+            matches.push((range, replacement));
+        }
+        matches
+    }
+}
+
+/// Where to continue searching after `range`, stepping over one character
+/// when the match was empty so the search always makes progress.
+pub fn skip_match(text: &str, range: &Range<usize>) -> usize {
+    if range.is_empty() {
+        range.end + text[range.end..].chars().next().map_or(1, char::len_utf8)
+    } else {
+        range.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(replacer: &Replacer, text: &str, start: usize, limit: usize) -> Vec<Range<usize>> {
+        replacer
+            .find_all(text, start, limit)
+            .into_iter()
+            .map(|(range, _)| range)
+            .collect()
+    }
+
+    #[test]
+    fn limit_does_not_act_as_end_of_text() {
+        let text = "foo foobar foo";
+        let line_end = Replacer::new("foo$", "x", true).unwrap();
+        assert!(ranges(&line_end, text, 0, 7).is_empty());
+        assert_eq!(ranges(&line_end, text, 0, text.len()), vec![11..14]);
+
+        let word = Replacer::new(r"\bfoo\b", "x", true).unwrap();
+        assert_eq!(ranges(&word, text, 0, 7), vec![0..3]);
+    }
+
+    #[test]
+    fn matches_must_end_before_limit() {
+        let replacer = Replacer::new("ab", "x", false).unwrap();
+        assert_eq!(ranges(&replacer, "ab ab ab", 1, 7), vec![3..5]);
+    }
+
+    #[test]
+    fn expands_captures() {
+        let replacer = Replacer::new(r"(\w+)=(\w+)", "$2=$1\\n", true).unwrap();
+        assert_eq!(
+            replacer.find_at("a=b c=d", 1, 7),
+            Some((4..7, "d=c\n".to_string()))
+        );
+    }
+}
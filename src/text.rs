@@ -0,0 +1,108 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Graphemes of `line` with the character index each one starts at.
+pub fn graphemes(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.graphemes(true).scan(0, |index, g| {
+        let start = *index;
+        *index += g.chars().count();
+        Some((start, g))
+    })
+}
+
+/// Screen cells taken by a grapheme starting at display `column`, measured
+/// the same way ratatui does so the cursor lines up with the rendered text.
+/// A tab stretches to the next multiple of `tab_width`.
+pub fn width(grapheme: &str, column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - column % tab_width
+    } else {
+        grapheme.width()
+    }
+}
+
+/// Display column of the character at `index`. An index inside a grapheme
+/// maps to the column where that grapheme starts.
+pub fn column_of(line: &str, index: usize, tab_width: usize) -> usize {
+    let mut column = 0;
+    for (start, g) in graphemes(line) {
+        if start + g.chars().count() > index {
+            break;
+        }
+        column += width(g, column, tab_width);
+    }
+    column
+}
+
+/// Character index of the grapheme covering display `column`, or the line
+/// length when the column is past its end.
+pub fn index_at_column(line: &str, column: usize, tab_width: usize) -> usize {
+    let mut current = 0;
+    for (start, g) in graphemes(line) {
+        current += width(g, current, tab_width);
+        if current > column {
+            return start;
+        }
+    }
+    line.chars().count()
+}
+
+pub fn next_grapheme(line: &str, index: usize) -> usize {
+    graphemes(line)
+        .map(|(start, g)| start + g.chars().count())
+        .find(|&end| end > index)
+        .unwrap_or(index)
+}
+
+pub fn previous_grapheme(line: &str, index: usize) -> usize {
+    graphemes(line)
+        .map(|(start, _)| start)
+        .take_while(|&start| start < index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Character indices where each screen row of `line` starts when it is
+/// wrapped to `columns` cells. With `at_words` rows break after whitespace
+/// when possible instead of in the middle of a word.
+pub fn wrap_points(line: &str, columns: usize, tab_width: usize, at_words: bool) -> Vec<usize> {
+    let mut points = vec![0];
+    let mut row_column = 0;
+    let mut column = 0;
+    // Where a row could start after the last whitespace seen in this row.
+    let mut word_start = None;
+
+    for (start, g) in graphemes(line) {
+        let w = width(g, column, tab_width);
+        let is_space = g.chars().all(char::is_whitespace);
+        // Whitespace may hang past the edge when breaking at words.
+        let overflows =
+            |row_column| column + w > row_column + columns.max(1) && column > row_column;
+        if overflows(row_column) && !(at_words && is_space) {
+            if let (true, Some((point, point_column))) = (at_words, word_start) {
+                points.push(point);
+                row_column = point_column;
+            }
+            // Still too long, or no word boundary to break at.
+            if overflows(row_column) {
+                points.push(start);
+                row_column = column;
+            }
+            word_start = None;
+        }
+
+        column += w;
+        if is_space {
+            word_start = Some((start + g.chars().count(), column));
+        }
+    }
+    points
+}
+
+/// Index of the row in `points` that character `index` is drawn on.
+pub fn wrap_row(points: &[usize], index: usize) -> usize {
+    points
+        .partition_point(|&point| point <= index)
+        .saturating_sub(1)
+}
@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+use ratatui::style::Color;
+use serde::{de, Deserialize, Deserializer};
+
+/// Colors used to draw the editor. Each accepts a name such as `"blue"`,
+/// an index such as `"42"` or a hex value such as `"#80c0ff"`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(deserialize_with = "color")]
+    pub foreground: Color,
+    #[serde(deserialize_with = "color")]
+    pub background: Color,
+    #[serde(deserialize_with = "color")]
+    pub line_number: Color,
+    #[serde(deserialize_with = "color")]
+    pub current_line_number: Color,
+    #[serde(deserialize_with = "color")]
+    pub current_line: Color,
+    #[serde(deserialize_with = "color")]
+    pub selection: Color,
+    #[serde(deserialize_with = "color")]
+    pub search_match: Color,
+    #[serde(deserialize_with = "color")]
+    pub current_match: Color,
+    #[serde(deserialize_with = "color")]
+    pub status_bar: Color,
+    /// Text drawn on top of `foreground` or `current_match`.
+    #[serde(deserialize_with = "color")]
+    pub highlight_text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            foreground: Color::Rgb(128, 192, 255),
+            background: Color::Rgb(32, 32, 64),
+            line_number: Color::Rgb(64, 96, 128),
+            current_line_number: Color::Rgb(96, 128, 196),
+            current_line: Color::Rgb(64, 64, 96),
+            selection: Color::Rgb(64, 96, 160),
+            search_match: Color::Rgb(96, 80, 32),
+            current_match: Color::Rgb(224, 176, 64),
+            status_bar: Color::Rgb(92, 92, 128),
+            highlight_text: Color::Rgb(16, 16, 16),
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Color::from_str(&name).map_err(|_| {
+        de::Error::custom(format!(
+            "invalid color `{}`, expected a name, an index or #rrggbb",
+            name
+        ))
+    })
+}
@@ -1,13 +1,20 @@
 use crate::app::{App, AppResult};
 use crate::event::EventHandler;
 use crate::ui;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::Backend;
 use ratatui::layout::Rect;
 use ratatui::Terminal;
 use std::io::{self, Error};
 use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether `init` pushed keyboard enhancement flags that `reset` must pop.
+static ENHANCED_KEYS: AtomicBool = AtomicBool::new(false);
 
 #[derive(Debug)]
 pub struct Tui<B: Backend> {
@@ -22,7 +29,22 @@ impl<B: Backend> Tui<B> {
 
     pub fn init(&mut self) -> AppResult<()> {
         terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+
+        // Without this terminals send Ctrl + Shift + letter the same as
+        // Ctrl + letter, so bindings like Ctrl+Shift+Z never arrive.
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            crossterm::execute!(
+                io::stderr(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )?;
+            ENHANCED_KEYS.store(true, Ordering::SeqCst);
+        }
 
         let panic_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic| {
@@ -43,8 +65,16 @@ impl<B: Backend> Tui<B> {
     }
 
     fn reset() -> AppResult<()> {
+        if ENHANCED_KEYS.swap(false, Ordering::SeqCst) {
+            crossterm::execute!(io::stderr(), PopKeyboardEnhancementFlags)?;
+        }
         terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
         Ok(())
     }
 
@@ -1,93 +1,211 @@
+use std::cmp::{max, min};
+
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Style, Stylize},
-    text::Line,
-    widgets::{Block, Borders, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::Paragraph,
     Frame,
 };
 
-const RELATIVE_LINES: bool = true;
+const WRAP_MARKER: &str = "↪";
 
-use crate::app::App;
+use crate::{
+    app::{self, App, Position, PromptMode},
+    config::LineNumbers,
+    text,
+};
 
-pub fn render(app: &mut App, frame: &mut Frame) {
-    let numbers_width = app.line_numbers_width;
-    let content_width = (frame.size().width - numbers_width as u16 - 1) as usize;
+fn selected_columns(
+    selection: Option<(Position, Position)>,
+    y: usize,
+    len: usize,
+) -> Option<(usize, usize)> {
+    let (start, end) = selection?;
+    if y < start.y || y > end.y {
+        return None;
+    }
 
-    let pos = app.get_cursor_position();
+    let from = if y == start.y { start.x } else { 0 };
+    // Lines that continue into the selection also highlight their line break.
+    let to = if y == end.y { end.x } else { len + 1 };
+    Some((from, to))
+}
 
-    // TODO: remove highlight if select
-    // TODO: remove highlight if select
-    let content_lines: Vec<Line> = app
-        .content
-        .iter()
-        .enumerate()
-        .map(|(i, s)| {
-            if i == pos.y {
-                Line::from(format!("{:<content_width$}", s.to_string()))
-                    .style(Style::default().bg(Color::Rgb(64, 64, 96)))
-            } else {
-                Line::from(format!("{}", s.to_string()))
-            }
+/// Builds the part of a line visible after scrolling `offset` display
+/// columns, up to the character at `end`, grouping graphemes that share a
+/// style. `styles` has one entry per character plus one for the cell after
+/// the text, drawn only when styled. Tabs are expanded to spaces.
+fn visible_spans(
+    line: &str,
+    styles: &[Style],
+    offset: usize,
+    end: usize,
+    tab_width: usize,
+) -> Vec<Span<'static>> {
+    let len = styles.len() - 1;
+    let end_cell = (styles[len] != Style::default()).then_some((len, " ", 1));
+    let cells = text::graphemes(line)
+        .scan(0, |column, (i, g)| {
+            let width = text::width(g, *column, tab_width);
+            *column += width;
+            Some((i, g, width))
         })
-        .collect();
+        .chain(end_cell);
+
+    let mut spans = Vec::new();
+    let mut content = String::new();
+    let mut current = Style::default();
+    let mut column = 0;
+
+    for (index, grapheme, width) in cells {
+        if index >= end {
+            break;
+        }
+        let start = column;
+        column += width;
+        if start < offset && column <= offset {
+            continue;
+        }
+
+        if styles[index] != current {
+            if !content.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut content), current));
+            }
+            current = styles[index];
+        }
 
-    // TODO: remove highlight if select
-    let numbers = if RELATIVE_LINES {
-        if app.content.len() == 1 {
-            vec![1]
+        if start < offset || grapheme == "\t" {
+            // A wide grapheme cut by the left edge leaves blank cells.
+            content.push_str(&" ".repeat(column - max(start, offset)));
         } else {
-            (1..=pos.y)
-                .rev()
-                .chain(std::iter::once(pos.y + 1))
-                .chain(1..=app.content.len().saturating_sub(pos.y + 1))
-                .collect::<Vec<_>>()
+            content.push_str(grapheme);
         }
-    } else {
-        (1..=app.content.len()).collect::<Vec<_>>()
+    }
+    spans.push(Span::styled(content, current));
+    spans
+}
+
+fn render_pane(app: &App, frame: &mut Frame, pane: usize, area: Rect) {
+    let theme = &app.theme;
+    let focused = pane == app.focused_pane();
+    let (buffer, pos, offset) = app.pane_view(pane);
+    let document = &buffer.document;
+    let numbers_width = app::gutter_width(document.len_lines());
+    let content_width = (area.width as usize).saturating_sub(numbers_width + 1);
+
+    let gutter_area = Rect {
+        width: min(numbers_width as u16 + 1, area.width),
+        ..area
+    };
+    let text_area = Rect {
+        x: area.x + gutter_area.width,
+        width: area.width - gutter_area.width,
+        ..area
     };
 
-    // TODO: remove highlight if select
-    let line_numbers: Vec<Line> = numbers
-        .iter()
-        .enumerate()
-        .map(|(e, &i)| {
-            if e == pos.y {
-                Line::from(format!("{:<numbers_width$} ", i))
-                    .style(Style::default().fg(Color::Rgb(96, 128, 196)))
+    // Another pane may have shortened the document under this one.
+    let first_line = min(offset.y, document.len_lines() - 1);
+    let last_line = min(first_line + area.height as usize, document.len_lines());
+
+    let selection = if focused { app.selection() } else { None };
+    let matches: &[Position] = if focused { &app.search_matches } else { &[] };
+    let query_len = app.search_query.chars().count();
+
+    let selection_style = Style::default().bg(theme.selection);
+    let match_style = Style::default().bg(theme.search_match);
+    let current_match_style = Style::default()
+        .fg(theme.highlight_text)
+        .bg(theme.current_match);
+
+    let mut content_lines = Vec::new();
+    let mut line_numbers = Vec::new();
+
+    for i in first_line..last_line {
+        let line = document.line(i);
+        let len = line.chars().count();
+        let mut styles = vec![Style::default(); len + 1];
+
+        let first = matches.partition_point(|m| m.y < i);
+        for (index, m) in matches[first..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, m)| m.y == i)
+        {
+            let style = if app.search_index == Some(first + index) {
+                current_match_style
             } else {
-                Line::from(format!("{:>numbers_width$} ", i))
+                match_style
+            };
+            for cell in &mut styles[m.x..m.x + query_len] {
+                *cell = style;
             }
-        })
-        .collect();
+        }
 
-    // TODO: remove highlight if select
-    let filename_content: String = if !app.opened_filename.is_empty() {
-        if app.dirty {
-            format!("Filename: {} (modified)", app.opened_filename)
+        if let Some((from, to)) = selected_columns(selection, i, len) {
+            for cell in &mut styles[from..to] {
+                *cell = selection_style;
+            }
+        }
+
+        let points = if app.soft_wrap {
+            text::wrap_points(
+                &line,
+                max(content_width, 1),
+                app.tab_width,
+                app.wrap_at_words,
+            )
         } else {
-            format!("Filename: {} ", app.opened_filename)
+            vec![0]
+        };
+        for (row, &start) in points.iter().enumerate() {
+            let end = points.get(row + 1).copied().unwrap_or(len + 1);
+            let offset = if app.soft_wrap {
+                text::column_of(&line, start, app.tab_width)
+            } else {
+                offset.x
+            };
+            let mut spans = visible_spans(&line, &styles, offset, end, app.tab_width);
+
+            content_lines.push(if i == pos.y && selection.is_none() {
+                let used: usize = spans.iter().map(|s| s.width()).sum();
+                spans.push(Span::raw(" ".repeat(content_width.saturating_sub(used))));
+                Line::from(spans).style(Style::default().bg(theme.current_line))
+            } else {
+                Line::from(spans)
+            });
+
+            line_numbers.push(if row > 0 {
+                Line::from(format!("{:>numbers_width$} ", WRAP_MARKER))
+            } else if i == pos.y {
+                Line::from(format!("{:<numbers_width$} ", i + 1))
+                    .style(Style::default().fg(theme.current_line_number))
+            } else if app.line_numbers == LineNumbers::Relative {
+                Line::from(format!("{:>numbers_width$} ", i.abs_diff(pos.y)))
+            } else {
+                Line::from(format!("{:>numbers_width$} ", i + 1))
+            });
         }
-    } else if app.dirty {
-        "Not saved!".into()
-    } else {
-        "".into()
-    };
 
-    let filename_status = Line::from(filename_content)
-        .left_aligned()
-        .style(Style::default().bg(Color::Rgb(128, 192, 255)).bold());
+        if content_lines.len() >= area.height as usize {
+            break;
+        }
+    }
 
-    let cursor_position_status = Line::from(format!(" {:>2}:{:<2} ", pos.y + 1, pos.x + 1,))
-        .right_aligned()
-        .style(Style::default().bg(Color::Rgb(128, 192, 255)).bold());
+    frame.render_widget(
+        Paragraph::new(line_numbers)
+            .style(Style::default().fg(theme.line_number).bg(theme.background)),
+        gutter_area,
+    );
 
-    let status_line: Line = if app.is_prompt {
-        Line::from(format!("{:<}", app.prompt))
-    } else {
-        Line::from(format!("{}", app.status)).centered()
-    };
+    frame.render_widget(
+        Paragraph::new(content_lines)
+            .style(Style::default().fg(theme.foreground).bg(theme.background)),
+        text_area,
+    );
+}
 
+pub fn render(app: &mut App, frame: &mut Frame) {
     let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -98,57 +216,110 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         ])
         .split(frame.size());
 
-    let content_layout = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Length(5), Constraint::Min(0)])
-        .split(layout[1]);
-
     let status_bar_layout = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(layout[2]);
 
-    frame.render_widget(
-        Block::new()
-            .borders(Borders::TOP)
-            .title("RustEdit")
-            .title_alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .fg(Color::Rgb(128, 192, 255))
-                    .bg(Color::Rgb(32, 32, 64))
-                    .bold(),
-            ),
-        layout[0],
-    );
+    let theme = &app.theme;
 
-    frame.render_widget(
-        Paragraph::new(line_numbers)
-            .style(
-                Style::default()
-                    .fg(Color::Rgb(64, 96, 128))
-                    .bg(Color::Rgb(32, 32, 64)),
-            )
-            .scroll((app.cursor_offset.y as u16, app.cursor_offset.x as u16)),
-        content_layout[0],
-    );
+    let panes = app.pane_layout(layout[1]);
+    for &(pane, area) in &panes.panes {
+        render_pane(app, frame, pane, area);
+    }
+
+    for &separator in &panes.separators {
+        let line = if separator.width == 1 {
+            vec![Line::from("│"); separator.height as usize]
+        } else {
+            vec![Line::from("─".repeat(separator.width as usize))]
+        };
+        frame.render_widget(
+            Paragraph::new(line).style(Style::default().fg(theme.status_bar).bg(theme.background)),
+            separator,
+        );
+    }
+
+    let pos = app.get_cursor_position();
+
+    let filename_content: String = if !app.buffer.opened_filename.is_empty() {
+        if app.buffer.dirty {
+            format!("Filename: {} (modified)", app.buffer.opened_filename)
+        } else {
+            format!("Filename: {} ", app.buffer.opened_filename)
+        }
+    } else if app.buffer.dirty {
+        "Not saved!".into()
+    } else {
+        "".into()
+    };
+
+    let filename_status = Line::from(filename_content)
+        .left_aligned()
+        .style(Style::default().bg(theme.foreground).bold());
+
+    let line_ending = if app.buffer.format.mixed {
+        format!("Mixed ({})", app.buffer.format.line_ending)
+    } else {
+        app.buffer.format.line_ending.to_string()
+    };
+    let encoding = if app.buffer.bom {
+        format!("{} BOM", app.buffer.encoding)
+    } else {
+        app.buffer.encoding.to_string()
+    };
+    let cursor_position_status = Line::from(format!(
+        " {}  {}  {:>2}:{:<2} ",
+        encoding,
+        line_ending,
+        pos.y + 1,
+        app.cursor_column() + 1
+    ))
+    .right_aligned()
+    .style(Style::default().bg(theme.foreground).bold());
+
+    let status_line: Line = if app.is_prompt {
+        Line::from(format!("{}{}", app.prompt_label(), app.prompt))
+    } else {
+        Line::from(app.status.clone()).centered()
+    };
+
+    let tabs: Vec<Span> = (0..app.buffer_count())
+        .map(|i| {
+            let buffer = app.buffer_at(i);
+            let label = if buffer.dirty {
+                format!(" {}* ", buffer.name())
+            } else {
+                format!(" {} ", buffer.name())
+            };
+            if i == app.current_buffer() {
+                Span::styled(
+                    label,
+                    Style::default()
+                        .fg(theme.highlight_text)
+                        .bg(theme.foreground),
+                )
+            } else {
+                Span::raw(label)
+            }
+        })
+        .collect();
 
     frame.render_widget(
-        Paragraph::new(content_lines)
-            .style(
-                Style::default()
-                    .fg(Color::Rgb(128, 192, 255))
-                    .bg(Color::Rgb(32, 32, 64)),
-            )
-            .scroll((app.cursor_offset.y as u16, app.cursor_offset.x as u16)),
-        content_layout[1],
+        Paragraph::new(Line::from(tabs)).style(
+            Style::default()
+                .fg(theme.foreground)
+                .bg(theme.background)
+                .bold(),
+        ),
+        layout[0],
     );
 
     frame.render_widget(
         Paragraph::new(filename_status).style(
             Style::default()
-                .fg(Color::Rgb(16, 16, 16))
-                .bg(Color::Rgb(92, 92, 128)),
+                .fg(theme.highlight_text)
+                .bg(theme.status_bar),
         ),
         status_bar_layout[0],
     );
@@ -156,16 +327,67 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     frame.render_widget(
         Paragraph::new(cursor_position_status).style(
             Style::default()
-                .fg(Color::Rgb(16, 16, 16))
-                .bg(Color::Rgb(92, 92, 128)),
+                .fg(theme.highlight_text)
+                .bg(theme.status_bar),
         ),
         status_bar_layout[1],
     );
 
-    frame.render_widget(status_line, layout[3]);
+    // Bare `Line`s panic when truncating wide characters, `Paragraph` doesn't.
+    frame.render_widget(Paragraph::new(status_line), layout[3]);
 
-    frame.set_cursor(
-        (app.cursor_position.x + (if app.is_prompt { 0 } else { numbers_width + 1 })) as u16,
-        app.cursor_position.y as u16 + 1,
-    );
+    if app.is_prompt && app.prompt_mode == PromptMode::Search {
+        frame.render_widget(
+            Paragraph::new(Line::from(format!("{} ", app.search_status())).right_aligned()),
+            layout[3],
+        );
+    }
+
+    if app.is_prompt {
+        frame.set_cursor(
+            (app.prompt_label().len()
+                + text::column_of(&app.prompt, app.buffer.cursor_position.x, app.tab_width))
+                as u16,
+            layout[3].y,
+        );
+    } else {
+        let area = panes
+            .panes
+            .iter()
+            .find(|&&(pane, _)| pane == app.focused_pane())
+            .map_or(layout[1], |&(_, area)| area);
+        let cell = app.cursor_cell();
+        frame.set_cursor(
+            area.x + (cell.x + app.line_numbers_width + 1) as u16,
+            area.y + cell.y as u16,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+
+    fn draw(app: &mut App, width: u16) {
+        let mut terminal = Terminal::new(TestBackend::new(width, 6)).unwrap();
+        app.window_size = Rect::new(0, 0, width, 6);
+        terminal.draw(|frame| render(app, frame)).unwrap();
+    }
+
+    #[test]
+    fn narrow_status_line_with_wide_characters() {
+        for width in 1..24 {
+            let mut app = App::new();
+            app.status = "日本語のステータス e\u{301}e\u{301}".into();
+            draw(&mut app, width);
+
+            app.start_search();
+            for c in "日本語e\u{301}".chars() {
+                app.insert_char(c);
+            }
+            draw(&mut app, width);
+        }
+    }
 }
@@ -0,0 +1,123 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Character ranges of the words in `line`, following Unicode word
+/// segmentation. Runs of punctuation count as words, whitespace does not.
+fn words(line: &str) -> Vec<(usize, usize)> {
+    let mut words: Vec<(usize, usize)> = Vec::new();
+    let mut column = 0;
+    let mut after_punctuation = false;
+
+    for segment in line.split_word_bounds() {
+        let len = segment.chars().count();
+        let punctuation = !segment.chars().any(char::is_alphanumeric);
+        if segment.chars().all(char::is_whitespace) {
+            after_punctuation = false;
+        } else {
+            // Segmentation splits punctuation into single characters, so
+            // `->` or `...` have to be joined back up.
+            match words.last_mut() {
+                Some(word) if punctuation && after_punctuation => word.1 += len,
+                _ => words.push((column, column + len)),
+            }
+            after_punctuation = punctuation;
+        }
+        column += len;
+    }
+    words
+}
+
+/// Column at the end of the word after `column`, or `None` at line end.
+pub fn next_boundary(line: &str, column: usize) -> Option<usize> {
+    let len = line.chars().count();
+    if column >= len {
+        return None;
+    }
+
+    Some(
+        words(line)
+            .into_iter()
+            .find(|&(_, end)| end > column)
+            .map_or(len, |(_, end)| end),
+    )
+}
+
+/// Column at the start of the word before `column`, or `None` at column 0.
+pub fn previous_boundary(line: &str, column: usize) -> Option<usize> {
+    if column == 0 {
+        return None;
+    }
+
+    Some(
+        words(line)
+            .into_iter()
+            .rev()
+            .find(|&(start, _)| start < column)
+            .map_or(0, |(start, _)| start),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward(line: &str) -> Vec<usize> {
+        let mut stops = Vec::new();
+        let mut column = 0;
+        while let Some(next) = next_boundary(line, column) {
+            stops.push(next);
+            column = next;
+        }
+        stops
+    }
+
+    fn backward(line: &str) -> Vec<usize> {
+        let mut stops = Vec::new();
+        let mut column = line.chars().count();
+        while let Some(previous) = previous_boundary(line, column) {
+            stops.push(previous);
+            column = previous;
+        }
+        stops
+    }
+
+    #[test]
+    fn punctuation_runs_are_one_word() {
+        assert_eq!(
+            words("a->b foo...bar"),
+            [(0, 1), (1, 3), (3, 4), (5, 8), (8, 11), (11, 14)]
+        );
+        assert_eq!(forward("a->b foo...bar"), [1, 3, 4, 8, 11, 14]);
+        assert_eq!(backward("a->b foo...bar"), [11, 8, 5, 3, 1, 0]);
+    }
+
+    #[test]
+    fn whitespace_separates_punctuation() {
+        assert_eq!(words("x = -y"), [(0, 1), (2, 3), (4, 5), (5, 6)]);
+    }
+
+    #[test]
+    fn skips_whitespace() {
+        assert_eq!(next_boundary("  foo  bar", 0), Some(5));
+        assert_eq!(next_boundary("  foo  bar", 5), Some(10));
+        assert_eq!(previous_boundary("foo  bar  ", 10), Some(5));
+        assert_eq!(previous_boundary("foo  bar", 5), Some(0));
+    }
+
+    #[test]
+    fn trailing_and_leading_whitespace_reach_the_line_ends() {
+        assert_eq!(next_boundary("foo  ", 3), Some(5));
+        assert_eq!(previous_boundary("  foo", 2), Some(0));
+    }
+
+    #[test]
+    fn line_ends_return_none() {
+        assert_eq!(next_boundary("foo", 3), None);
+        assert_eq!(next_boundary("", 0), None);
+        assert_eq!(previous_boundary("foo", 0), None);
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(forward("héllo wörld"), [5, 11]);
+    }
+}